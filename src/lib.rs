@@ -0,0 +1,11 @@
+//! Raytracer for 3D scenes to generate beautiful images.
+//!
+//! Scenes are usually described in YAML files (see the README) and parsed with [Scene::from_yaml],
+//! or built in code with [Scene::new] and the constructors of the materials and lights.
+//! After [precomputing](Scene::precompute) it, [render] returns the image as a [Framebuffer] in memory,
+//! while [compute_image] loads a scene file and writes the image and its debug outputs to files.
+mod math;
+mod raytracer;
+
+pub use math::Vector3;
+pub use raytracer::*;
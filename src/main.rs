@@ -1,7 +1,5 @@
-mod math;
-mod raytracer;
-
 use std::path;
+use std::time::Duration;
 
 use clap::Parser;
 
@@ -12,15 +10,101 @@ struct Args {
     scene_path: String,
     #[clap(short, long)]
     output_path: String,
-    #[clap(short, long, default_value_t = 5)]
-    depth: u8,
+    #[clap(short, long, default_value_t = 64)]
+    depth: u32,
+    /// Number of bounces after which paths are terminated randomly by Russian roulette
+    #[clap(long, default_value_t = 3)]
+    rr_depth: u32,
     #[clap(long, required = false, default_value = "uniform:2")]
-    ssaa: raytracer::SuperSampling,
+    ssaa: raytracer_rust::SuperSampling,
+    /// Reconstruction filter for the pixel samples: box, tent, gaussian, mitchell or lanczos[:radius]
+    #[clap(long, default_value = "box")]
+    filter: raytracer_rust::Filter,
+    /// Light transport algorithm: path, direct, whitted or ao[:distance]
+    #[clap(long, default_value = "path")]
+    integrator: raytracer_rust::IntegratorType,
+    /// Sampler for all random decisions: independent, halton, sobol or bluenoise
+    #[clap(long, default_value = "independent")]
+    sampler: raytracer_rust::SamplerType,
+    /// Seed of all random numbers, renders with the same seed and options result in identical images
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    /// Adaptive sampling with min:max[:threshold] samples per pixel, replaces the fixed samples of `--ssaa`
+    #[clap(long)]
+    adaptive: Option<raytracer_rust::AdaptiveSampling>,
+    /// Write a heatmap of the samples spent per pixel next to the output image
+    #[clap(long)]
+    sample_heatmap: bool,
+    /// Render progressively until this many samples per pixel are reached, replaces the fixed samples of `--ssaa`
+    #[clap(long)]
+    target_spp: Option<usize>,
+    /// Render progressively until this many seconds passed, replaces the fixed samples of `--ssaa`
+    #[clap(long, parse(try_from_str = raytracer_rust::parse_seconds))]
+    time_limit: Option<Duration>,
+    /// Rewrite the output image of a progressive render every N passes or every Ns seconds
+    #[clap(long, default_value = "10s")]
+    write_interval: raytracer_rust::WriteInterval,
+    /// Number of worker threads rendering tiles of the image, 0 uses one thread per logical core
+    #[clap(long, default_value_t = 0)]
+    threads: usize,
+    /// Write the accumulated image to a checkpoint next to the output image every N seconds
+    #[clap(long, parse(try_from_str = raytracer_rust::parse_seconds))]
+    checkpoint_interval: Option<Duration>,
+    /// Continue the render from the checkpoint next to the output image,
+    /// the scene, its assets and the sampling options must be unchanged
+    #[clap(long)]
+    resume: bool,
+    /// Don't print the progress and statistics of the render
+    #[clap(short, long)]
+    quiet: bool,
+    /// Debug outputs written next to the output image: normal, depth, uv, material, object
+    #[clap(long, use_value_delimiter = true)]
+    aov: Vec<raytracer_rust::Aov>,
+    /// Only write the debug outputs selected by `--aov` and skip rendering the image itself
+    #[clap(long)]
+    aov_only: bool,
 }
 
 fn main() {
     let args = Args::parse();
     let scene_path = path::Path::new(&args.scene_path);
     let output_path = path::Path::new(&args.output_path);
-    raytracer::compute_image(args.ssaa, args.depth, scene_path, output_path);
+    let integrator = args.integrator.build(args.depth, args.rr_depth);
+    let progressive = progressive(&args);
+    let settings = raytracer_rust::RenderSettings {
+        ssaa: args.ssaa,
+        filter: args.filter,
+        sampler: args.sampler,
+        seed: args.seed,
+        adaptive: args.adaptive,
+        progressive,
+        threads: args.threads,
+        report_progress: !args.quiet,
+    };
+    let output = raytracer_rust::OutputSettings {
+        sample_heatmap: args.sample_heatmap,
+        checkpoint_interval: args.checkpoint_interval,
+        resume: args.resume,
+    };
+    raytracer_rust::compute_image(
+        &settings,
+        &output,
+        integrator.as_ref(),
+        &args.aov,
+        !args.aov_only,
+        scene_path,
+        output_path,
+    );
+}
+
+/// Returns the progressive render settings if a stop condition for a progressive render is given
+fn progressive(args: &Args) -> Option<raytracer_rust::Progressive> {
+    if args.target_spp.is_none() && args.time_limit.is_none() {
+        return None;
+    }
+    Some(raytracer_rust::Progressive {
+        target_spp: args.target_spp,
+        time_limit: args.time_limit,
+        write_interval: args.write_interval,
+    })
 }
@@ -0,0 +1,176 @@
+/// Piecewise constant 1D distribution over the interval `[0, 1)` used for importance sampling tabulated functions.
+#[derive(Clone, Debug)]
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    /// Creates a new distribution proportional to the given (non-negative) function values.
+    /// If all values are zero the distribution falls back to a uniform distribution.
+    ///
+    /// # Arguments
+    ///
+    /// * `func` function values of the equally sized segments of `[0, 1)`
+    pub fn new(func: Vec<f64>) -> Distribution1D {
+        let n = func.len();
+        let mut cdf = Vec::with_capacity(n + 1);
+        cdf.push(0.0);
+        for i in 0..n {
+            cdf.push(cdf[i] + func[i] / n as f64);
+        }
+        let integral = cdf[n];
+        for (i, value) in cdf.iter_mut().enumerate() {
+            *value = if integral == 0.0 {
+                i as f64 / n as f64
+            } else {
+                *value / integral
+            };
+        }
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    /// Returns the number of segments of the distribution
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Returns the integral of the tabulated function over `[0, 1)`
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    /// Maps a uniform random number to a sample of the distribution.
+    /// Returns the continuous sample in `[0, 1)`, its pdf and the index of the segment it lies in.
+    ///
+    /// # Arguments
+    ///
+    /// * `u` uniform random number in `[0, 1)`
+    pub fn sample_continuous(&self, u: f64) -> (f64, f64, usize) {
+        let idx = (self.cdf.partition_point(|&c| c <= u).max(1) - 1).min(self.count() - 1);
+        let segment = self.cdf[idx + 1] - self.cdf[idx];
+        let offset = if segment > 0.0 {
+            (u - self.cdf[idx]) / segment
+        } else {
+            0.0
+        };
+        let x = ((idx as f64 + offset) / self.count() as f64).min(1.0 - f64::EPSILON);
+        (x, self.pdf(idx), idx)
+    }
+
+    /// Returns the pdf of samples lying in the segment with the given index
+    ///
+    /// # Arguments
+    ///
+    /// * `idx` index of the segment
+    pub fn pdf(&self, idx: usize) -> f64 {
+        if self.integral == 0.0 {
+            return 1.0;
+        }
+        self.func[idx] / self.integral
+    }
+}
+
+/// Piecewise constant 2D distribution over `[0, 1)^2` built from a row-major table of function values.
+/// Samples first choose a row by the marginal distribution and then a column by the conditional distribution of that row.
+#[derive(Clone, Debug)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// Creates a new distribution proportional to the given (non-negative) function values.
+    ///
+    /// # Arguments
+    ///
+    /// * `func` function values in row -> column order
+    /// * `width` number of columns
+    /// * `height` number of rows
+    pub fn new(func: &[f64], width: usize, height: usize) -> Distribution2D {
+        let conditional: Vec<Distribution1D> = (0..height)
+            .map(|y| Distribution1D::new(func[y * width..(y + 1) * width].to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|d| d.integral()).collect());
+        Distribution2D {
+            conditional,
+            marginal,
+        }
+    }
+
+    /// Maps two uniform random numbers to a sample `(x, y)` of the distribution and returns it with its pdf.
+    ///
+    /// # Arguments
+    ///
+    /// * `u` uniform random numbers in `[0, 1)`
+    pub fn sample_continuous(&self, u: (f64, f64)) -> ((f64, f64), f64) {
+        let (y, pdf_y, row) = self.marginal.sample_continuous(u.1);
+        let (x, pdf_x, _) = self.conditional[row].sample_continuous(u.0);
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    /// Returns the pdf of the sample `(x, y)`
+    ///
+    /// # Arguments
+    ///
+    /// * `point` sample in `[0, 1)^2`
+    pub fn pdf(&self, point: (f64, f64)) -> f64 {
+        let width = self.conditional[0].count();
+        let height = self.marginal.count();
+        let x = ((point.0 * width as f64) as usize).min(width - 1);
+        let y = ((point.1 * height as f64) as usize).min(height - 1);
+        if self.marginal.integral() == 0.0 {
+            return 1.0;
+        }
+        self.conditional[y].func[x] / self.marginal.integral()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Distribution1D, Distribution2D};
+
+    #[test]
+    fn test_distribution_1d() {
+        let distribution = Distribution1D::new(vec![1.0, 3.0, 0.0, 4.0]);
+
+        assert_eq!(distribution.integral(), 2.0);
+        assert_eq!(distribution.pdf(0), 0.5);
+        assert_eq!(distribution.pdf(1), 1.5);
+        assert_eq!(distribution.pdf(2), 0.0);
+
+        let (x, pdf, idx) = distribution.sample_continuous(0.0625);
+        assert_eq!((x, pdf, idx), (0.125, 0.5, 0));
+        let (x, pdf, idx) = distribution.sample_continuous(0.125);
+        assert_eq!((x, pdf, idx), (0.25, 1.5, 1));
+        let (x, _, idx) = distribution.sample_continuous(0.75);
+        assert_eq!((x, idx), (0.875, 3));
+    }
+
+    #[test]
+    fn test_distribution_1d_zero() {
+        let distribution = Distribution1D::new(vec![0.0, 0.0]);
+
+        let (x, pdf, idx) = distribution.sample_continuous(0.75);
+        assert_eq!((x, pdf, idx), (0.75, 1.0, 1));
+    }
+
+    #[test]
+    fn test_distribution_2d() {
+        let distribution = Distribution2D::new(&[0.0, 1.0, 2.0, 1.0], 2, 2);
+
+        let ((x, y), pdf) = distribution.sample_continuous((0.5, 0.1));
+        assert!(x >= 0.5 && y < 0.5);
+        assert_eq!(pdf, distribution.pdf((x, y)));
+        assert_eq!(pdf, 1.0);
+
+        let ((x, y), pdf) = distribution.sample_continuous((0.1, 0.9));
+        assert!(x < 0.5 && y >= 0.5);
+        assert_eq!(pdf, 2.0);
+    }
+}
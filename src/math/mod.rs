@@ -1,3 +1,5 @@
+mod distribution;
 mod vec3;
 
+pub use distribution::*;
 pub use vec3::*;
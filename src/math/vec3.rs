@@ -1,4 +1,3 @@
-use rand::Rng;
 use serde::{Deserialize, Deserializer};
 use std::ops::{self, Index, IndexMut};
 
@@ -21,34 +20,37 @@ impl Vector3 {
         Vector3 { data: [x, y, z] }
     }
 
-    /// Creates a random vector with each coordinate in the given bounds.
+    /// Maps a uniform sample of `[0, 1)²` to a uniformly distributed unit vector
     ///
     /// # Arguments
     ///
-    /// * `min` minimum of the rng range
-    /// * `max` maximum of the rng range (inclusive)
-    pub fn random(min: f64, max: f64) -> Vector3 {
-        let mut rng = rand::thread_rng();
-        Vector3::new(
-            rng.gen_range(min..=max),
-            rng.gen_range(min..=max),
-            rng.gen_range(min..=max),
-        )
+    /// * `u` uniform sample (e.g. from a [Sampler](crate::Sampler))
+    pub fn sample_unit_vector(u: (f64, f64)) -> Vector3 {
+        let z = 1.0 - 2.0 * u.0;
+        let r = f64::sqrt(f64::max(0.0, 1.0 - z * z));
+        let phi = 2.0 * std::f64::consts::PI * u.1;
+        Vector3::new(r * phi.cos(), r * phi.sin(), z)
     }
 
-    /// Creates a random vector in the unit sphere
-    pub fn random_in_unit_sphere() -> Vector3 {
-        loop {
-            let v = Vector3::random(-1.0, 1.0);
-            if v.sqr_len() < 1.0 {
-                return v;
-            }
-        }
+    /// Maps uniform samples to a uniformly distributed vector in the unit sphere
+    ///
+    /// # Arguments
+    ///
+    /// * `u` uniform sample of the direction
+    /// * `u_radius` uniform sample of the distance to the center
+    pub fn sample_in_unit_sphere(u: (f64, f64), u_radius: f64) -> Vector3 {
+        Vector3::sample_unit_vector(u) * u_radius.cbrt()
     }
 
-    /// Creates a random unit vector
-    pub fn random_unit_vector() -> Vector3 {
-        Vector3::random_in_unit_sphere().normalized()
+    /// Maps a uniform sample of `[0, 1)²` to a uniformly distributed vector in the unit disk of the xy-plane
+    ///
+    /// # Arguments
+    ///
+    /// * `u` uniform sample of the radius and angle
+    pub fn sample_in_unit_disk(u: (f64, f64)) -> Vector3 {
+        let r = u.0.sqrt();
+        let phi = 2.0 * std::f64::consts::PI * u.1;
+        Vector3::new(r * phi.cos(), r * phi.sin(), 0.0)
     }
 
     /// Checks if the vector is near zero meaning all it's coordinates are close to being 0.0
@@ -117,7 +119,7 @@ impl Vector3 {
         if norm != 0.0 {
             return Vector3::new(self[0] / norm, self[1] / norm, self[2] / norm);
         }
-        self.clone()
+        *self
     }
 
     /// Reflects the vector for the normal `n`.
@@ -130,6 +132,23 @@ impl Vector3 {
         *self - *n * (2.0 * self.dot(n))
     }
 
+    /// Returns two unit vectors which form an orthonormal basis together with `self`.
+    /// `self` is expected to be normalized.
+    /// The construction follows "Building an Orthonormal Basis, Revisited" by Duff et al.
+    pub fn orthonormal_basis(&self) -> (Vector3, Vector3) {
+        let sign = 1.0_f64.copysign(self[2]);
+        let a = -1.0 / (sign + self[2]);
+        let b = self[0] * self[1] * a;
+        (
+            Vector3::new(
+                1.0 + sign * self[0] * self[0] * a,
+                sign * b,
+                -sign * self[0],
+            ),
+            Vector3::new(b, sign + self[1] * self[1] * a, -self[1]),
+        )
+    }
+
     /// Returns a new Vector representing the minimum of both
     /// vectors coordinate-wise.
     ///
@@ -327,6 +346,27 @@ mod test {
         assert_eq!(neg, Vector3::new(-1.0, -1.0, -1.0), "wrong negation");
     }
 
+    #[test]
+    fn test_orthonormal_basis() {
+        for n in [
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            Vector3::new(1.0, 2.0, 3.0).normalized(),
+            Vector3::new(-0.5, 0.1, -0.2).normalized(),
+        ] {
+            let (t, b) = n.orthonormal_basis();
+
+            assert!((t.len() - 1.0).abs() < 1e-12, "tangent not normalized");
+            assert!((b.len() - 1.0).abs() < 1e-12, "bitangent not normalized");
+            assert!(t.dot(&n).abs() < 1e-12, "tangent not orthogonal");
+            assert!(b.dot(&n).abs() < 1e-12, "bitangent not orthogonal");
+            assert!(
+                t.dot(&b).abs() < 1e-12,
+                "tangent and bitangent not orthogonal"
+            );
+        }
+    }
+
     #[test]
     fn test_near_zero() {
         let positive = Vector3::new(0.0000000000001, 0.0000000000001, 0.0000000000001);
@@ -337,11 +377,28 @@ mod test {
     }
 
     #[test]
-    fn test_random_unit_vector() {
-        let vec = Vector3::random_unit_vector();
+    fn test_sample_in_unit_disk() {
+        for i in 0..100 {
+            let u = (i as f64 / 100.0, (i * 37 % 100) as f64 / 100.0);
+            let vec = Vector3::sample_in_unit_disk(u);
+
+            assert_eq!(vec.z(), 0.0);
+            assert!(vec.sqr_len() < 1.0);
+        }
+    }
 
-        let len = vec.len();
+    #[test]
+    fn test_sample_unit_vector() {
+        for u in [(0.0, 0.0), (0.25, 0.5), (0.5, 0.1), (0.999, 0.999)] {
+            let vec = Vector3::sample_unit_vector(u);
+            assert!((vec.len() - 1.0).abs() < 1e-9);
 
-        assert!(len >= 9e-6 && len <= 1.0 + 1e-6);
+            let inside = Vector3::sample_in_unit_sphere(u, 0.125);
+            assert!((inside.len() - 0.5).abs() < 1e-9);
+        }
+        assert_eq!(
+            Vector3::sample_unit_vector((0.0, 0.0)),
+            Vector3::new(0.0, 0.0, 1.0)
+        );
     }
 }
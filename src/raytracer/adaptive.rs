@@ -0,0 +1,238 @@
+use crate::raytracer::image::Color;
+
+/// Luminance below which the error of a pixel is measured absolutely instead of relative to its mean,
+/// so dark pixels don't receive the maximum number of samples just because their mean is close to zero
+const DARK_LUMINANCE: f64 = 0.01;
+
+/// Adaptive sampling which takes samples of a pixel until the estimated error of its mean is small enough.
+/// Every pixel receives at least `min_samples` and at most `max_samples` samples. In between a pixel is considered
+/// converged once the standard error of its mean luminance relative to the mean falls below `threshold`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct AdaptiveSampling {
+    pub min_samples: usize,
+    pub max_samples: usize,
+    pub threshold: f64,
+}
+
+impl AdaptiveSampling {
+    /// Returns `true` if no more samples should be taken for a pixel with the given statistics
+    pub fn is_converged(&self, statistics: &PixelStatistics) -> bool {
+        if statistics.count < self.min_samples {
+            return false;
+        }
+        statistics.count >= self.max_samples || statistics.relative_error() < self.threshold
+    }
+}
+
+/// Running mean and variance of the luminance of the samples of a pixel (Welford's algorithm)
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct PixelStatistics {
+    pub(crate) count: usize,
+    pub(crate) mean: f64,
+    pub(crate) squared_deviations: f64,
+}
+
+impl PixelStatistics {
+    /// Returns the number of samples taken
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Adds the radiance of a sample
+    pub fn add(&mut self, color: Color) {
+        let luminance = color.luminance();
+        self.count += 1;
+        let delta = luminance - self.mean;
+        self.mean += delta / self.count as f64;
+        self.squared_deviations += delta * (luminance - self.mean);
+    }
+
+    /// Returns the unbiased sample variance of the luminance
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        self.squared_deviations / (self.count - 1) as f64
+    }
+
+    /// Returns the standard error of the mean luminance relative to the mean
+    pub fn relative_error(&self) -> f64 {
+        if self.count < 2 {
+            return f64::INFINITY;
+        }
+        let standard_error = (self.variance() / self.count as f64).sqrt();
+        standard_error / self.mean.abs().max(DARK_LUMINANCE)
+    }
+}
+
+/// Error Type for Decoding the AdaptiveSampling settings from a String
+pub struct AdaptiveDecodeError {
+    error: String,
+}
+
+impl AdaptiveDecodeError {
+    fn new(error: String) -> AdaptiveDecodeError {
+        AdaptiveDecodeError { error }
+    }
+}
+
+impl std::error::Error for AdaptiveDecodeError {}
+
+impl std::fmt::Display for AdaptiveDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing adaptive argument: {}", self.error)
+    }
+}
+
+impl std::fmt::Debug for AdaptiveDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing adaptive argument: {}", self.error)
+    }
+}
+
+impl std::str::FromStr for AdaptiveSampling {
+    type Err = AdaptiveDecodeError;
+
+    /// Parses `min:max[:threshold]`, where the threshold defaults to `0.01`
+    fn from_str(s: &str) -> Result<AdaptiveSampling, AdaptiveDecodeError> {
+        let args = s.split(':').collect::<Vec<_>>();
+        if args.len() < 2 || args.len() > 3 {
+            return Err(AdaptiveDecodeError::new(
+                "expected min:max[:threshold]".to_string(),
+            ));
+        }
+        let parse_count = |arg: &str| {
+            arg.parse::<usize>().map_err(|_| {
+                AdaptiveDecodeError::new("sample counts have to be integers".to_string())
+            })
+        };
+        let min_samples = parse_count(args[0])?;
+        let max_samples = parse_count(args[1])?;
+        if min_samples < 2 || max_samples < min_samples {
+            return Err(AdaptiveDecodeError::new(
+                "min has to be at least 2 and max at least min".to_string(),
+            ));
+        }
+        let threshold = match args.get(2) {
+            Some(arg) => match arg.parse::<f64>() {
+                Ok(threshold) if threshold > 0.0 => threshold,
+                _ => {
+                    return Err(AdaptiveDecodeError::new(
+                        "threshold has to be a positive number".to_string(),
+                    ))
+                }
+            },
+            None => 0.01,
+        };
+        Ok(AdaptiveSampling {
+            min_samples,
+            max_samples,
+            threshold,
+        })
+    }
+}
+
+/// Maps the number of samples of each pixel to a heatmap going from black over blue, red and yellow to white
+/// for `max_samples`.
+///
+/// # Arguments
+///
+/// * `sample_counts` number of samples of each pixel
+/// * `max_samples` number of samples mapped to white
+pub fn sample_heatmap(sample_counts: &[usize], max_samples: usize) -> Vec<Color> {
+    const KEYS: [(f64, f64, f64); 5] = [
+        (0.0, 0.0, 0.0),
+        (0.0, 0.0, 1.0),
+        (1.0, 0.0, 0.0),
+        (1.0, 1.0, 0.0),
+        (1.0, 1.0, 1.0),
+    ];
+    sample_counts
+        .iter()
+        .map(|&count| {
+            let t = (count as f64 / max_samples.max(1) as f64).min(1.0) * (KEYS.len() - 1) as f64;
+            let idx = (t as usize).min(KEYS.len() - 2);
+            let f = t - idx as f64;
+            let (a, b) = (KEYS[idx], KEYS[idx + 1]);
+            Color::new(
+                a.0 + f * (b.0 - a.0),
+                a.1 + f * (b.1 - a.1),
+                a.2 + f * (b.2 - a.2),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::raytracer::image::Color;
+
+    use super::{sample_heatmap, AdaptiveSampling, PixelStatistics};
+
+    #[test]
+    fn test_pixel_statistics() {
+        let mut statistics = PixelStatistics::default();
+        for value in [1.0, 2.0, 3.0, 4.0] {
+            statistics.add(Color::new(value, value, value));
+        }
+        assert!((statistics.mean - 2.5).abs() < 1e-12);
+        assert!((statistics.variance() - 5.0 / 3.0).abs() < 1e-12);
+        assert!((statistics.relative_error() - (5.0f64 / 12.0).sqrt() / 2.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_convergence() {
+        let adaptive = AdaptiveSampling {
+            min_samples: 4,
+            max_samples: 8,
+            threshold: 0.01,
+        };
+        // constant pixels stop after the minimum number of samples
+        let mut constant = PixelStatistics::default();
+        while !adaptive.is_converged(&constant) {
+            constant.add(Color::new(0.5, 0.5, 0.5));
+        }
+        assert_eq!(constant.count, 4);
+
+        // noisy pixels use the maximum number of samples
+        let mut noisy = PixelStatistics::default();
+        let mut value = 0.0;
+        while !adaptive.is_converged(&noisy) {
+            noisy.add(Color::new(value, value, value));
+            value = 1.0 - value;
+        }
+        assert_eq!(noisy.count, 8);
+    }
+
+    #[test]
+    fn test_sample_heatmap() {
+        let heatmap = sample_heatmap(&[0, 2, 4, 8], 4);
+        assert_eq!(heatmap[0], Color::new(0.0, 0.0, 0.0));
+        assert_eq!(heatmap[1], Color::new(1.0, 0.0, 0.0));
+        assert_eq!(heatmap[2], Color::new(1.0, 1.0, 1.0));
+        assert_eq!(heatmap[3], Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_adaptive_from_str() {
+        assert_eq!(
+            AdaptiveSampling::from_str("16:256").unwrap(),
+            AdaptiveSampling {
+                min_samples: 16,
+                max_samples: 256,
+                threshold: 0.01
+            }
+        );
+        assert_eq!(
+            AdaptiveSampling::from_str("4:64:0.05").unwrap().threshold,
+            0.05
+        );
+        assert!(AdaptiveSampling::from_str("16").is_err());
+        assert!(AdaptiveSampling::from_str("1:16").is_err());
+        assert!(AdaptiveSampling::from_str("32:16").is_err());
+        assert!(AdaptiveSampling::from_str("4:16:-1").is_err());
+        assert!(AdaptiveSampling::from_str("a:16").is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use rand::Rng;
+use crate::raytracer::sampler::{hash, to_unit};
 
 /// Enum representing different SuperSampling techniques
 #[derive(Debug)]
@@ -9,16 +9,44 @@ pub enum SuperSampling {
 }
 
 impl SuperSampling {
-    pub fn sample(&self, x: usize, y: usize) -> Vec<(f64, f64)> {
+    /// Returns the sample positions of a pixel
+    ///
+    /// # Arguments
+    ///
+    /// * `x` coordinate of the pixel on the x-axis
+    /// * `y` coordinate of the pixel on the y-axis
+    /// * `seed` seed of the render, the random offsets of a pixel only depend on the seed and the pixel
+    pub fn sample(&self, x: usize, y: usize, seed: u64) -> Vec<(f64, f64)> {
         match self {
             SuperSampling::Uniform(resolution) => {
                 uniform_grid_sampling(*resolution, x as f64, y as f64)
             }
-            SuperSampling::Jitter(resolution) => jitter_sampling(*resolution, x as f64, y as f64),
+            SuperSampling::Jitter(resolution) => jitter_sampling(
+                *resolution,
+                x as f64,
+                y as f64,
+                hash(&[seed, x as u64, y as u64]),
+            ),
         }
     }
 }
 
+/// Returns the position of the `index`-th sample of a pixel placed uniformly at random inside the pixel.
+/// The position only depends on the seed, the pixel and the index, which is used when the number of samples of a
+/// pixel isn't known in advance.
+///
+/// # Arguments
+///
+/// * `x` coordinate of the pixel on the x-axis
+/// * `y` coordinate of the pixel on the y-axis
+/// * `index` index of the sample in the pixel
+/// * `seed` seed of the render
+pub fn random_sample(x: usize, y: usize, index: usize, seed: u64) -> (f64, f64) {
+    let offset =
+        |dimension: u64| to_unit(hash(&[seed, x as u64, y as u64, index as u64, dimension]));
+    (x as f64 + offset(0), y as f64 + offset(1))
+}
+
 /// Error Type for Decoding a SuperSampling-Variant from a String
 pub struct SSAADecodeError {
     error: String,
@@ -92,7 +120,7 @@ impl std::str::FromStr for SuperSampling {
 /// * `base_y` lower left y-coordinate of the grid
 fn uniform_grid_sampling(resolution: usize, base_x: f64, base_y: f64) -> Vec<(f64, f64)> {
     let step: f64 = 1.0 / resolution as f64;
-    let mut samples = Vec::with_capacity((resolution * resolution) as usize);
+    let mut samples = Vec::with_capacity(resolution * resolution);
     for i in 0..resolution {
         for j in 0..resolution {
             samples.push((base_x + i as f64 * step, base_y + j as f64 * step));
@@ -108,15 +136,17 @@ fn uniform_grid_sampling(resolution: usize, base_x: f64, base_y: f64) -> Vec<(f6
 /// * `resolution` Resolution of the grid to span for the pixel
 /// * `base_x` lower left x-coordinate of the grid
 /// * `base_y` lower left y-coordinate of the grid
-fn jitter_sampling(resolution: usize, base_x: f64, base_y: f64) -> Vec<(f64, f64)> {
-    let mut rng = rand::thread_rng();
+/// * `key` hash from which the offsets inside the grid cells are derived
+fn jitter_sampling(resolution: usize, base_x: f64, base_y: f64, key: u64) -> Vec<(f64, f64)> {
     let step: f64 = 1.0 / resolution as f64;
+    let offset = |dimension: usize| to_unit(hash(&[key, dimension as u64])) * step;
     let mut samples = Vec::with_capacity(resolution * resolution);
     for i in 0..resolution {
         for j in 0..resolution {
+            let cell = 2 * (i * resolution + j);
             samples.push((
-                base_x + (i as f64 * step) + rng.gen_range(0.0..step),
-                base_y + (j as f64 * step) + rng.gen_range(0.0..step),
+                base_x + (i as f64 * step) + offset(cell),
+                base_y + (j as f64 * step) + offset(cell + 1),
             ));
         }
     }
@@ -181,7 +211,7 @@ mod tests {
 
     #[test]
     fn test_jitter_sampling() {
-        let samples = super::jitter_sampling(2, 0.0, 0.0);
+        let samples = super::jitter_sampling(2, 0.0, 0.0, 42);
         assert_eq!(
             samples.len(),
             4,
@@ -214,6 +244,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_random_sample() {
+        for index in 0..16 {
+            let (x, y) = super::random_sample(3, 5, index, 0);
+            assert!((3.0..4.0).contains(&x) && (5.0..6.0).contains(&y));
+            assert_eq!(super::random_sample(3, 5, index, 0), (x, y));
+        }
+        assert_ne!(
+            super::random_sample(3, 5, 0, 0),
+            super::random_sample(3, 5, 0, 1)
+        );
+    }
+
     #[test]
     fn test_ssaa_uniform_from_str() {
         let valid_uniform = super::SuperSampling::from_str("uniform:2");
@@ -225,7 +268,7 @@ mod tests {
             "expected from_str to return ok got {:?}",
             valid_uniform.err().unwrap()
         );
-        let valid_samples_len = valid_uniform.unwrap().sample(0, 0).len();
+        let valid_samples_len = valid_uniform.unwrap().sample(0, 0, 0).len();
 
         assert_eq!(
             valid_samples_len,
@@ -259,7 +302,7 @@ mod tests {
             "expected from_str to return ok got {:?}",
             valid_jitter.err().unwrap()
         );
-        let valid_samples_len = valid_jitter.unwrap().sample(0, 0).len();
+        let valid_samples_len = valid_jitter.unwrap().sample(0, 0, 0).len();
 
         assert_eq!(
             valid_samples_len,
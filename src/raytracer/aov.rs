@@ -0,0 +1,226 @@
+use rayon::prelude::*;
+use std::path;
+
+use crate::raytracer::camera::Camera;
+use crate::raytracer::image::{self, Color};
+use crate::raytracer::scene::{intersections::IntersectionInfo, Scene};
+
+/// Arbitrary output variables which visualize the primary intersections of the camera rays as false colors.
+/// They are meant for debugging scenes and are traced through the pixel centers without anti aliasing.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Aov {
+    /// Shading normal mapped from `[-1, 1]` to `[0, 1]`
+    Normal,
+    /// Hit distance `t` normalized by the largest distance in the image
+    Depth,
+    /// Texture coordinates as red and green channel (black for objects without texture coordinates)
+    Uv,
+    /// Distinct color per material index, i.e. per entry of a mesh's material list (`0` for spheres and planes)
+    Material,
+    /// Distinct color per object
+    Object,
+}
+
+impl Aov {
+    /// Returns the name of the AOV used on the command line and for output files
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Normal => "normal",
+            Aov::Depth => "depth",
+            Aov::Uv => "uv",
+            Aov::Material => "material",
+            Aov::Object => "object",
+        }
+    }
+
+    /// Returns the path the AOV is written to next to the beauty image, e.g. `out_normal.png` for `out.png`
+    ///
+    /// # Arguments
+    ///
+    /// * `output_path` Path of the beauty image
+    pub fn output_path(&self, output_path: &path::Path) -> path::PathBuf {
+        image::suffixed_path(output_path, self.name())
+    }
+
+    /// Renders the AOV by tracing a single ray through the center of each pixel.
+    /// Returns the false colors in row -> column order, starting with the top row.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` The current (precomputed) scene
+    /// * `camera` camera to spawn the primary rays with
+    pub fn render(&self, scene: &Scene, camera: &Camera) -> Vec<Color> {
+        let mut pixel_colors: Vec<Color> = (0..scene.width * scene.height)
+            .into_par_iter()
+            .map(|idx| {
+                let i = idx % scene.width;
+                let j = scene.height - 1 - idx / scene.width;
+                let intersection = camera
+                    .spawn_ray(i as f64 + 0.5, j as f64 + 0.5, (0.0, 0.0), 0.0)
+                    .and_then(|ray| scene.get_closest_interesection(&ray));
+                self.evaluate(intersection.as_ref())
+            })
+            .collect();
+
+        if *self == Aov::Depth {
+            let max_t = pixel_colors.iter().map(|c| c.r).fold(0.0, f64::max);
+            if max_t > 0.0 {
+                for color in pixel_colors.iter_mut() {
+                    *color = *color * (1.0 / max_t);
+                }
+            }
+        }
+        pixel_colors
+    }
+
+    /// Returns the false color of a single intersection (or black if the ray missed).
+    /// The depth is returned unnormalized.
+    fn evaluate(&self, intersection: Option<&IntersectionInfo>) -> Color {
+        let info = match intersection {
+            Some(info) => info,
+            None => return Color::new(0.0, 0.0, 0.0),
+        };
+        match self {
+            Aov::Normal => {
+                let normal = info.normal.normalized();
+                Color::new(
+                    0.5 * (normal.x() + 1.0),
+                    0.5 * (normal.y() + 1.0),
+                    0.5 * (normal.z() + 1.0),
+                )
+            }
+            Aov::Depth => Color::new(info.t, info.t, info.t),
+            Aov::Uv => match (info.u, info.v) {
+                (Some(u), Some(v)) => Color::new(u.rem_euclid(1.0), v.rem_euclid(1.0), 0.0),
+                _ => Color::new(0.0, 0.0, 0.0),
+            },
+            Aov::Material => id_color(info.material_idx as u64),
+            Aov::Object => id_color(info.object_idx as u64),
+        }
+    }
+}
+
+/// Maps an id to a bright pseudo random color so neighbouring ids are easy to distinguish
+fn id_color(id: u64) -> Color {
+    // finalizer of the splitmix64 generator
+    let mut hash = id.wrapping_add(0x9e3779b97f4a7c15);
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^= hash >> 31;
+    let channel = |shift: u64| 0.2 + 0.8 * ((hash >> shift) & 0xff) as f64 / 255.0;
+    Color::new(channel(0), channel(8), channel(16))
+}
+
+/// Error Type for Decoding an Aov-Variant from a String
+pub struct AovDecodeError {
+    error: String,
+}
+
+impl std::error::Error for AovDecodeError {}
+
+impl std::fmt::Display for AovDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing aov argument: {}", self.error)
+    }
+}
+
+impl std::fmt::Debug for AovDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing aov argument: {}", self.error)
+    }
+}
+
+impl std::str::FromStr for Aov {
+    type Err = AovDecodeError;
+
+    fn from_str(s: &str) -> Result<Aov, AovDecodeError> {
+        match s {
+            "normal" => Ok(Aov::Normal),
+            "depth" => Ok(Aov::Depth),
+            "uv" => Ok(Aov::Uv),
+            "material" => Ok(Aov::Material),
+            "object" => Ok(Aov::Object),
+            _ => Err(AovDecodeError {
+                error: format!("unknown aov {}", s),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use crate::{
+        math::Vector3,
+        raytracer::{
+            image::Color,
+            scene::{
+                intersections::IntersectionInfo,
+                materials::{LambertianMaterial, Material},
+            },
+        },
+    };
+
+    use super::{id_color, Aov};
+
+    #[test]
+    fn test_parse_aov() {
+        for aov in [Aov::Normal, Aov::Depth, Aov::Uv, Aov::Material, Aov::Object] {
+            assert_eq!(aov.name().parse::<Aov>().unwrap(), aov);
+        }
+        assert!("albedo".parse::<Aov>().is_err());
+    }
+
+    #[test]
+    fn test_output_path() {
+        assert_eq!(
+            Aov::Normal.output_path(Path::new("renders/out.png")),
+            PathBuf::from("renders/out_normal.png")
+        );
+        assert_eq!(
+            Aov::Depth.output_path(Path::new("out")),
+            PathBuf::from("out_depth")
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let material = Material::Lambertian(LambertianMaterial::new(Color::new(1.0, 1.0, 1.0)));
+        let mut info = IntersectionInfo::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            &material,
+            2.5,
+        );
+
+        assert_eq!(Aov::Normal.evaluate(Some(&info)), Color::new(0.5, 0.0, 0.5));
+        assert_eq!(Aov::Depth.evaluate(Some(&info)), Color::new(2.5, 2.5, 2.5));
+        assert_eq!(Aov::Uv.evaluate(Some(&info)), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(Aov::Object.evaluate(None), Color::new(0.0, 0.0, 0.0));
+
+        info.u = Some(0.25);
+        info.v = Some(1.75);
+        assert_eq!(Aov::Uv.evaluate(Some(&info)), Color::new(0.25, 0.75, 0.0));
+
+        let object = Aov::Object.evaluate(Some(&info));
+        let material_color = Aov::Material.evaluate(Some(&info));
+        info.material_idx = 1;
+        assert_eq!(Aov::Object.evaluate(Some(&info)), object);
+        assert!(Aov::Material.evaluate(Some(&info)) != material_color);
+        // the same material index has the same color on every object
+        let material_color = Aov::Material.evaluate(Some(&info));
+        info.object_idx = 3;
+        assert_eq!(Aov::Material.evaluate(Some(&info)), material_color);
+        assert!(Aov::Object.evaluate(Some(&info)) != object);
+    }
+
+    #[test]
+    fn test_id_color() {
+        let colors: Vec<Color> = (0..16).map(id_color).collect();
+        for (i, a) in colors.iter().enumerate() {
+            assert!(a.r >= 0.2 && a.g >= 0.2 && a.b >= 0.2);
+            assert!(colors[i + 1..].iter().all(|b| a != b));
+        }
+    }
+}
@@ -1,68 +1,499 @@
 use crate::math;
 use crate::raytracer::raytrace::Ray;
+use crate::raytracer::scene::{CameraConfig, Projection, StereoConfig};
+
+/// Camera spawning the primary rays for the pixels of the image.
+/// The type of camera is chosen by the `projection` of the [CameraConfig].
+pub enum Camera {
+    Projective(ProjectiveCamera),
+    Equirectangular(EquirectangularCamera),
+    Fisheye(FisheyeCamera),
+}
+
+impl Camera {
+    /// Constructs the camera of the configured projection for an image of the given size.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` camera configuration of the scene
+    /// * `width` width of the image
+    /// * `height` height of the image
+    pub fn new(config: &CameraConfig, width: usize, height: usize) -> Camera {
+        match config.projection {
+            Projection::Perspective | Projection::Orthographic => {
+                Camera::Projective(ProjectiveCamera::new(config, width, height))
+            }
+            Projection::Equirectangular => {
+                Camera::Equirectangular(EquirectangularCamera::new(config, width, height))
+            }
+            Projection::Fisheye => Camera::Fisheye(FisheyeCamera::new(config, width, height)),
+        }
+    }
+
+    /// Constructs the cameras of the left and right eye of a stereo pair (see [StereoConfig]).
+    ///
+    /// # Arguments
+    ///
+    /// * `config` camera configuration of the scene
+    /// * `stereo` stereo configuration of the camera
+    /// * `width` width of the image of each eye
+    /// * `height` height of the image of each eye
+    pub fn new_stereo(
+        config: &CameraConfig,
+        stereo: &StereoConfig,
+        width: usize,
+        height: usize,
+    ) -> (Camera, Camera) {
+        let basis = CameraBasis::new(config);
+        let view_distance = (config.look_at - config.eye).len();
+        let convergence = stereo
+            .convergence
+            .or(config.focus_distance)
+            .unwrap_or(view_distance);
+        let offset = basis.right * (0.5 * stereo.interocular_distance);
+
+        let mut left = Camera::new(config, width, height);
+        left.move_eye(-offset, convergence);
+        let mut right = Camera::new(config, width, height);
+        right.move_eye(offset, convergence);
+        (left, right)
+    }
+
+    /// Moves the eye of the camera without changing its viewing direction.
+    /// Perspective cameras shift their viewing frustum so that the image plane at the `convergence` distance stays in place.
+    fn move_eye(&mut self, offset: math::Vector3, convergence: f64) {
+        match self {
+            Camera::Projective(c) => c.move_eye(offset, convergence),
+            Camera::Equirectangular(c) => c.eye = c.eye + offset,
+            Camera::Fisheye(c) => c.eye = c.eye + offset,
+        }
+    }
+
+    /// Spawns a new primary ray for a given pixel tracing from the camera.
+    /// Returns `None` if the pixel is not covered by the projection (e.g. outside the image circle of a fisheye).
+    ///
+    /// # Arguments
+    ///
+    /// * `x` coordinate of the pixel on the x-axis
+    /// * `y` coordinate of the pxiel on the y-axis
+    /// * `u_lens` uniform sample of the point on the lens
+    /// * `u_time` uniform sample of the point in time while the shutter is open
+    pub fn spawn_ray(&self, x: f64, y: f64, u_lens: (f64, f64), u_time: f64) -> Option<Ray> {
+        let time = self.shutter().sample_time(u_time);
+        match self {
+            Camera::Projective(c) => Some(c.spawn_ray(x, y, u_lens, time)),
+            Camera::Equirectangular(c) => Some(c.spawn_ray(x, y, time)),
+            Camera::Fisheye(c) => c.spawn_ray(x, y, time),
+        }
+    }
+
+    fn shutter(&self) -> &Shutter {
+        match self {
+            Camera::Projective(c) => &c.shutter,
+            Camera::Equirectangular(c) => &c.shutter,
+            Camera::Fisheye(c) => &c.shutter,
+        }
+    }
+}
+
+/// Interval of the frame time in which the shutter of a camera is open
+struct Shutter {
+    open: f64,
+    close: f64,
+}
+
+impl Shutter {
+    fn new(config: &CameraConfig) -> Shutter {
+        Shutter {
+            open: config.shutter_open,
+            close: config.shutter_close.max(config.shutter_open),
+        }
+    }
+
+    /// Maps a uniform sample to a point in time while the shutter is open
+    fn sample_time(&self, u: f64) -> f64 {
+        self.open + u * (self.close - self.open)
+    }
+}
+
+/// Orthonormal basis of a camera with the `forward` axis pointing from the eye towards the look at point
+struct CameraBasis {
+    forward: math::Vector3,
+    right: math::Vector3,
+    up: math::Vector3,
+}
+
+impl CameraBasis {
+    fn new(config: &CameraConfig) -> CameraBasis {
+        let forward = (config.look_at - config.eye).normalized();
+        let right = forward.cross(&config.up).normalized();
+        let up = right.cross(&forward);
+        CameraBasis { forward, right, up }
+    }
+}
 
 /// Representation of a camera in 3D space.
 /// A camera is set at an origin / eye point with a certain viewing frustum constrained
 /// by the lower left corner and horizontal / vertical bounds.
-pub struct Camera {
+/// Cameras with an aperture act as thin lens: rays start on a disk around the eye and are focused on the
+/// plane at the focus distance, where the viewing frustum is spanned.
+/// Rays are spawned at random points in time while the shutter is open.
+/// Orthographic cameras span the image plane around the eye instead and spawn parallel rays from it,
+/// they do not support an aperture.
+pub struct ProjectiveCamera {
+    projection: Projection,
     eye: math::Vector3,
+    view: math::Vector3,
     horizontal: math::Vector3,
     vertical: math::Vector3,
     lower_left: math::Vector3,
+    lens_radius: f64,
+    /// distance between the eye and the plane spanned by `lower_left`, `horizontal` and `vertical`
+    plane_distance: f64,
+    shutter: Shutter,
 }
 
-impl Camera {
+impl ProjectiveCamera {
     /// Constructs a new camera with the given parameters.
     /// Calculates the needed vectors to create the viewing frustum.
     ///
     /// # Arguments
     ///
-    /// * `eye` the origin point / eye of the camera
-    /// * `look_at` the center / look at point of the camera in the scene
-    /// * `up` up vector of the camera (normal)
-    /// * `fovy` used to calculate the spacial image height (different from the actual height of the image file)
+    /// * `config` camera configuration of the scene
     /// * `width` width of the image
     /// * `height` height of the image
-    pub fn new(
-        eye: math::Vector3,
-        look_at: math::Vector3,
-        up: math::Vector3,
-        fovy: f64,
-        width: usize,
-        height: usize,
-    ) -> Camera {
-        let view_vec = look_at - eye;
-        let distance = view_vec.len();
-        let view = view_vec / distance;
+    pub fn new(config: &CameraConfig, width: usize, height: usize) -> ProjectiveCamera {
+        let view_vec = config.look_at - config.eye;
+        let view = view_vec.normalized();
+        let distance = config.focus_distance.unwrap_or_else(|| view_vec.len());
         let aspect = width as f64 / height as f64;
 
-        let image_height = 2.0 * distance * (0.5 * fovy.to_radians()).tan();
+        let perspective_height =
+            |distance: f64| 2.0 * distance * (0.5 * config.field_of_view().to_radians()).tan();
+        let (image_height, plane_distance) = match config.projection {
+            Projection::Orthographic => (
+                config
+                    .ortho_height
+                    .unwrap_or_else(|| perspective_height(view_vec.len())),
+                0.0,
+            ),
+            _ => (perspective_height(distance), distance),
+        };
+        let center = config.eye + view * plane_distance;
         let image_width = image_height * aspect;
 
-        let horizontal = view.cross(&up).normalized() * image_width / width as f64;
+        let horizontal = view.cross(&config.up).normalized() * image_width / width as f64;
         let vertical = horizontal.cross(&view).normalized() * image_height / height as f64;
 
         let lower_left =
-            look_at - horizontal * (0.5 * width as f64) - vertical * (0.5 * height as f64);
+            center - horizontal * (0.5 * width as f64) - vertical * (0.5 * height as f64);
 
-        Camera {
-            eye,
+        ProjectiveCamera {
+            projection: config.projection,
+            eye: config.eye,
+            view,
             horizontal,
             vertical,
             lower_left,
+            lens_radius: 0.5 * config.lens_diameter(),
+            plane_distance,
+            shutter: Shutter::new(config),
         }
     }
 
+    /// Moves the eye by `offset` and shifts the image plane so that it matches the one of the original eye
+    /// at the `convergence` distance
+    fn move_eye(&mut self, offset: math::Vector3, convergence: f64) {
+        self.eye = self.eye + offset;
+        self.lower_left = self.lower_left + offset * (1.0 - self.plane_distance / convergence);
+    }
+
     /// Spawns a new primary ray for a given pixel tracing from the camera.
     ///
     /// # Arguments
     ///
     /// * `x` coordinate of the pixel on the x-axis
     /// * `y` coordinate of the pxiel on the y-axis
-    pub fn spawn_ray(&self, x: f64, y: f64) -> Ray {
-        Ray::new(
-            self.eye,
-            self.lower_left + self.horizontal * x + self.vertical * y - self.eye,
-        )
+    /// * `u_lens` uniform sample of the point on the lens
+    /// * `time` point in time of the ray
+    pub fn spawn_ray(&self, x: f64, y: f64, u_lens: (f64, f64), time: f64) -> Ray {
+        let target = self.lower_left + self.horizontal * x + self.vertical * y;
+        if self.projection == Projection::Orthographic {
+            return Ray::with_time(target, self.view, time);
+        }
+
+        let origin = if self.lens_radius > 0.0 {
+            let lens = math::Vector3::sample_in_unit_disk(u_lens) * self.lens_radius;
+            self.eye
+                + self.horizontal.normalized() * lens.x()
+                + self.vertical.normalized() * lens.y()
+        } else {
+            self.eye
+        };
+        Ray::with_time(origin, target - origin, time)
+    }
+}
+
+/// Panoramic camera capturing the full sphere of directions around the eye in a lat-long image.
+/// The center of the image looks towards `look_at`, the horizontal axis covers 360 degrees of longitude
+/// and the vertical axis 180 degrees of latitude.
+pub struct EquirectangularCamera {
+    eye: math::Vector3,
+    basis: CameraBasis,
+    width: f64,
+    height: f64,
+    shutter: Shutter,
+}
+
+impl EquirectangularCamera {
+    /// Constructs a new equirectangular camera for an image of the given size
+    ///
+    /// # Arguments
+    ///
+    /// * `config` camera configuration of the scene
+    /// * `width` width of the image
+    /// * `height` height of the image
+    pub fn new(config: &CameraConfig, width: usize, height: usize) -> EquirectangularCamera {
+        EquirectangularCamera {
+            eye: config.eye,
+            basis: CameraBasis::new(config),
+            width: width as f64,
+            height: height as f64,
+            shutter: Shutter::new(config),
+        }
+    }
+
+    /// Spawns a new primary ray for a given pixel tracing from the camera.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` coordinate of the pixel on the x-axis
+    /// * `y` coordinate of the pxiel on the y-axis
+    /// * `time` point in time of the ray
+    pub fn spawn_ray(&self, x: f64, y: f64, time: f64) -> Ray {
+        let phi = (x / self.width - 0.5) * 2.0 * std::f64::consts::PI;
+        let theta = (y / self.height - 0.5) * std::f64::consts::PI;
+        let direction = self.basis.forward * (theta.cos() * phi.cos())
+            + self.basis.right * (theta.cos() * phi.sin())
+            + self.basis.up * theta.sin();
+        Ray::with_time(self.eye, direction, time)
+    }
+}
+
+/// Equidistant fisheye camera projecting a cone of directions around the viewing direction onto a circle
+/// inscribed into the image. The angle to the viewing direction grows linearly with the distance to the image center
+/// and reaches half of the field of view at the border of the circle.
+pub struct FisheyeCamera {
+    eye: math::Vector3,
+    basis: CameraBasis,
+    center: (f64, f64),
+    radius: f64,
+    half_fov: f64,
+    shutter: Shutter,
+}
+
+impl FisheyeCamera {
+    /// Constructs a new fisheye camera for an image of the given size
+    ///
+    /// # Arguments
+    ///
+    /// * `config` camera configuration of the scene
+    /// * `width` width of the image
+    /// * `height` height of the image
+    pub fn new(config: &CameraConfig, width: usize, height: usize) -> FisheyeCamera {
+        FisheyeCamera {
+            eye: config.eye,
+            basis: CameraBasis::new(config),
+            center: (0.5 * width as f64, 0.5 * height as f64),
+            radius: 0.5 * width.min(height) as f64,
+            half_fov: 0.5 * config.fisheye_fov.to_radians(),
+            shutter: Shutter::new(config),
+        }
+    }
+
+    /// Spawns a new primary ray for a given pixel tracing from the camera.
+    /// Returns `None` for pixels outside the image circle.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` coordinate of the pixel on the x-axis
+    /// * `y` coordinate of the pxiel on the y-axis
+    /// * `time` point in time of the ray
+    pub fn spawn_ray(&self, x: f64, y: f64, time: f64) -> Option<Ray> {
+        let dx = (x - self.center.0) / self.radius;
+        let dy = (y - self.center.1) / self.radius;
+        let r = f64::sqrt(dx * dx + dy * dy);
+        if r > 1.0 {
+            return None;
+        }
+
+        let theta = r * self.half_fov;
+        let phi = dy.atan2(dx);
+        let direction = self.basis.forward * theta.cos()
+            + (self.basis.right * phi.cos() + self.basis.up * phi.sin()) * theta.sin();
+        Some(Ray::with_time(self.eye, direction, time))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        math::Vector3,
+        raytracer::{
+            raytrace::Ray,
+            sampler::{hash, to_unit},
+            scene::{CameraConfig, Projection, StereoConfig, StereoLayout},
+        },
+    };
+
+    use super::Camera;
+
+    /// Spawns a ray with the lens and time samples derived from `index`
+    fn spawn(camera: &Camera, x: f64, y: f64, index: u64) -> Option<Ray> {
+        let u = |dimension: u64| to_unit(hash(&[index, dimension]));
+        camera.spawn_ray(x, y, (u(0), u(1)), u(2))
+    }
+
+    fn config(aperture: f64, focus_distance: Option<f64>) -> CameraConfig {
+        CameraConfig {
+            eye: Vector3::new(0.0, 0.0, 0.0),
+            look_at: Vector3::new(0.0, 0.0, -4.0),
+            up: Vector3::new(0.0, 1.0, 0.0),
+            fovy: 90.0,
+            projection: Projection::Perspective,
+            ortho_height: None,
+            fisheye_fov: 180.0,
+            aperture,
+            focus_distance,
+            shutter_open: 0.25,
+            shutter_close: 0.75,
+            physical: None,
+            stereo: None,
+        }
+    }
+
+    #[test]
+    fn test_pinhole_camera() {
+        let camera = Camera::new(&config(0.0, None), 10, 10);
+        let ray = spawn(&camera, 5.0, 5.0, 0).unwrap();
+
+        assert_eq!(ray.origin, Vector3::new(0.0, 0.0, 0.0));
+        assert!((ray.direction - Vector3::new(0.0, 0.0, -1.0)).len() < 1e-12);
+
+        // the top right corner lies at 45 degrees in both directions
+        let corner = spawn(&camera, 10.0, 10.0, 0).unwrap();
+        let expected = Vector3::new(1.0, 1.0, -1.0).normalized();
+        assert!((corner.direction - expected).len() < 1e-12);
+    }
+
+    #[test]
+    fn test_orthographic_camera() {
+        let mut config = config(0.0, None);
+        config.projection = Projection::Orthographic;
+        config.ortho_height = Some(2.0);
+        let camera = Camera::new(&config, 20, 10);
+
+        let center = spawn(&camera, 10.0, 5.0, 0).unwrap();
+        assert!((center.origin - Vector3::new(0.0, 0.0, 0.0)).len() < 1e-12);
+        assert_eq!(center.direction, Vector3::new(0.0, 0.0, -1.0));
+
+        let corner = spawn(&camera, 20.0, 10.0, 0).unwrap();
+        assert!((corner.origin - Vector3::new(2.0, 1.0, 0.0)).len() < 1e-12);
+        assert_eq!(corner.direction, Vector3::new(0.0, 0.0, -1.0));
+
+        // without an explicit height the frustum height at the look at point is used
+        config.ortho_height = None;
+        let camera = Camera::new(&config, 10, 10);
+        let corner = spawn(&camera, 10.0, 10.0, 0).unwrap();
+        assert!((corner.origin - Vector3::new(4.0, 4.0, 0.0)).len() < 1e-12);
+    }
+
+    #[test]
+    fn test_shutter() {
+        let camera = Camera::new(&config(0.0, None), 10, 10);
+        for index in 0..50 {
+            let ray = spawn(&camera, 5.0, 5.0, index).unwrap();
+            assert!((0.25..0.75).contains(&ray.time));
+        }
+    }
+
+    #[test]
+    fn test_thin_lens_focus() {
+        for focus_distance in [None, Some(2.5)] {
+            let camera = Camera::new(&config(0.5, focus_distance), 10, 10);
+            let distance = focus_distance.unwrap_or(4.0);
+            let focus_point = Vector3::new(0.4 * distance, 0.0, -distance);
+            for index in 0..50 {
+                let ray = spawn(&camera, 7.0, 5.0, index).unwrap();
+                assert!(ray.origin.len() <= 0.25);
+                assert_eq!(ray.origin.z(), 0.0);
+
+                // all rays through a pixel meet on the focus plane
+                let t = distance / -ray.direction.z();
+                assert!((ray.at_timestep(t) - focus_point).len() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_equirectangular_camera() {
+        let mut config = config(0.0, None);
+        config.projection = Projection::Equirectangular;
+        let camera = Camera::new(&config, 40, 20);
+
+        let center = spawn(&camera, 20.0, 10.0, 0).unwrap();
+        assert_eq!(center.origin, Vector3::new(0.0, 0.0, 0.0));
+        assert!((center.direction - Vector3::new(0.0, 0.0, -1.0)).len() < 1e-12);
+
+        let right = spawn(&camera, 30.0, 10.0, 0).unwrap();
+        assert!((right.direction - Vector3::new(1.0, 0.0, 0.0)).len() < 1e-12);
+
+        let behind = spawn(&camera, 0.0, 10.0, 0).unwrap();
+        assert!((behind.direction - Vector3::new(0.0, 0.0, 1.0)).len() < 1e-12);
+
+        let top = spawn(&camera, 20.0, 20.0, 0).unwrap();
+        assert!((top.direction - Vector3::new(0.0, 1.0, 0.0)).len() < 1e-12);
+    }
+
+    #[test]
+    fn test_fisheye_camera() {
+        let mut config = config(0.0, None);
+        config.projection = Projection::Fisheye;
+        config.fisheye_fov = 180.0;
+        let camera = Camera::new(&config, 20, 10);
+
+        let center = spawn(&camera, 10.0, 5.0, 0).unwrap();
+        assert!((center.direction - Vector3::new(0.0, 0.0, -1.0)).len() < 1e-12);
+
+        // the border of the image circle looks perpendicular to the viewing direction
+        let top = spawn(&camera, 10.0, 10.0, 0).unwrap();
+        assert!((top.direction - Vector3::new(0.0, 1.0, 0.0)).len() < 1e-12);
+
+        // halfway to the border lies at a quarter of the field of view
+        let right = spawn(&camera, 12.5, 5.0, 0).unwrap();
+        let expected = Vector3::new(1.0, 0.0, -1.0).normalized();
+        assert!((right.direction - expected).len() < 1e-12);
+
+        assert!(spawn(&camera, 16.0, 5.0, 0).is_none());
+    }
+
+    #[test]
+    fn test_stereo_camera() {
+        let stereo = StereoConfig {
+            interocular_distance: 0.2,
+            convergence: Some(2.0),
+            layout: StereoLayout::SideBySide,
+        };
+        for focus_distance in [None, Some(3.0)] {
+            let (left, right) = Camera::new_stereo(&config(0.0, focus_distance), &stereo, 10, 10);
+            for (camera, x) in [(left, -0.1), (right, 0.1)] {
+                let ray = spawn(&camera, 5.0, 5.0, 0).unwrap();
+                assert!((ray.origin - Vector3::new(x, 0.0, 0.0)).len() < 1e-12);
+
+                // both eyes see the center of the image at the convergence distance
+                let t = 2.0 / -ray.direction.z();
+                assert!((ray.at_timestep(t) - Vector3::new(0.0, 0.0, -2.0)).len() < 1e-9);
+            }
+        }
     }
 }
@@ -0,0 +1,335 @@
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path;
+
+use crate::raytracer::adaptive::PixelStatistics;
+use crate::raytracer::film::FilmPixel;
+use crate::raytracer::image::Color;
+use crate::raytracer::integrator::Integrator;
+use crate::raytracer::raytrace::RenderSettings;
+use crate::raytracer::sampler::hash;
+
+/// Identifies checkpoint files and the version of their layout
+const MAGIC: &[u8; 8] = b"RTCKPT02";
+
+/// Keys of the scene file whose values are paths of files loaded with the scene, i.e. meshes, textures and
+/// environment maps
+const ASSET_KEYS: [&str; 2] = ["path", "texture_path"];
+
+/// Accumulated state of an unfinished render from which it can be resumed.
+/// Samplers derive all random numbers from the seed, the pixel and the index of a sample, so the seed together with
+/// the sample counts of the pixels fully describes the state of the random numbers.
+#[derive(Debug, PartialEq)]
+pub struct Checkpoint {
+    /// hash of the scene file and its assets the render was started with (see [scene_hash])
+    pub scene_hash: u64,
+    /// hash of the render settings and integrator which determine the samples (see [settings_hash])
+    pub settings_hash: u64,
+    /// seed of the render
+    pub seed: u64,
+    /// number of completed passes
+    pub passes: usize,
+    /// state of the image of each camera
+    pub cameras: Vec<CameraCheckpoint>,
+}
+
+/// Accumulated state of the image of a single camera
+#[derive(Debug, PartialEq)]
+pub struct CameraCheckpoint {
+    /// index of the next tile to render in the current pass
+    pub next_tile: usize,
+    /// weighted sums of the film in its own pixel order
+    pub film: Vec<FilmPixel>,
+    /// statistics of the samples of the pixels of all tiles in the order of the tiles
+    pub statistics: Vec<PixelStatistics>,
+}
+
+impl Checkpoint {
+    /// Returns the path the checkpoint of a render is written to, e.g. `out.checkpoint` for `out.png`
+    ///
+    /// # Arguments
+    ///
+    /// * `output_path` Path of the output image
+    pub fn path(output_path: &path::Path) -> path::PathBuf {
+        output_path.with_extension("checkpoint")
+    }
+
+    /// Writes the checkpoint to a temporary file which then replaces the file at `path`,
+    /// so a render killed while writing doesn't destroy the previous checkpoint
+    pub fn write(&self, path: &path::Path) -> io::Result<()> {
+        let temporary_path = path.with_extension("checkpoint.tmp");
+        {
+            let mut writer = BufWriter::new(fs::File::create(&temporary_path)?);
+            writer.write_all(MAGIC)?;
+            write_u64(&mut writer, self.scene_hash)?;
+            write_u64(&mut writer, self.settings_hash)?;
+            write_u64(&mut writer, self.seed)?;
+            write_u64(&mut writer, self.passes as u64)?;
+            write_u64(&mut writer, self.cameras.len() as u64)?;
+            for camera in &self.cameras {
+                write_u64(&mut writer, camera.next_tile as u64)?;
+                write_u64(&mut writer, camera.film.len() as u64)?;
+                for pixel in &camera.film {
+                    write_f64(&mut writer, pixel.color.r)?;
+                    write_f64(&mut writer, pixel.color.g)?;
+                    write_f64(&mut writer, pixel.color.b)?;
+                    write_f64(&mut writer, pixel.weight)?;
+                }
+                write_u64(&mut writer, camera.statistics.len() as u64)?;
+                for statistics in &camera.statistics {
+                    write_u64(&mut writer, statistics.count as u64)?;
+                    write_f64(&mut writer, statistics.mean)?;
+                    write_f64(&mut writer, statistics.squared_deviations)?;
+                }
+            }
+            writer.flush()?;
+        }
+        fs::rename(temporary_path, path)
+    }
+
+    /// Reads a checkpoint written by [write](Self::write)
+    pub fn read(path: &path::Path) -> io::Result<Checkpoint> {
+        let mut reader = BufReader::new(fs::File::open(path)?);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a render checkpoint",
+            ));
+        }
+        let scene_hash = read_u64(&mut reader)?;
+        let settings_hash = read_u64(&mut reader)?;
+        let seed = read_u64(&mut reader)?;
+        let passes = read_u64(&mut reader)? as usize;
+        let camera_count = read_u64(&mut reader)?;
+        let mut cameras = Vec::new();
+        for _ in 0..camera_count {
+            let next_tile = read_u64(&mut reader)? as usize;
+            let film_len = read_u64(&mut reader)?;
+            let mut film = Vec::new();
+            for _ in 0..film_len {
+                film.push(FilmPixel {
+                    color: Color::new(
+                        read_f64(&mut reader)?,
+                        read_f64(&mut reader)?,
+                        read_f64(&mut reader)?,
+                    ),
+                    weight: read_f64(&mut reader)?,
+                });
+            }
+            let statistics_len = read_u64(&mut reader)?;
+            let mut statistics = Vec::new();
+            for _ in 0..statistics_len {
+                statistics.push(PixelStatistics {
+                    count: read_u64(&mut reader)? as usize,
+                    mean: read_f64(&mut reader)?,
+                    squared_deviations: read_f64(&mut reader)?,
+                });
+            }
+            cameras.push(CameraCheckpoint {
+                next_tile,
+                film,
+                statistics,
+            });
+        }
+        Ok(Checkpoint {
+            scene_hash,
+            settings_hash,
+            seed,
+            passes,
+            cameras,
+        })
+    }
+}
+
+/// Returns a hash of the contents of a scene file and all files it references, i.e. meshes, textures and
+/// environment maps, so a checkpoint is rejected if any of them was edited
+///
+/// # Arguments
+///
+/// * `scene_source` contents of the scene file
+///
+/// # Errors
+///
+/// If the scene isn't valid YAML or a referenced file can't be read
+pub fn scene_hash(scene_source: &[u8]) -> io::Result<u64> {
+    let value: serde_yaml::Value = serde_yaml::from_slice(scene_source)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    let mut paths = Vec::new();
+    asset_paths(&value, &mut paths);
+    let mut scene_hash = content_hash(scene_source);
+    for path in paths {
+        scene_hash = hash(&[
+            scene_hash,
+            content_hash(path.as_bytes()),
+            content_hash(&fs::read(path)?),
+        ]);
+    }
+    Ok(scene_hash)
+}
+
+/// Collects the paths of all files referenced in a scene in the order they appear
+fn asset_paths<'a>(value: &'a serde_yaml::Value, paths: &mut Vec<&'a str>) {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                match (key.as_str(), value.as_str()) {
+                    (Some(key), Some(path)) if ASSET_KEYS.contains(&key) => paths.push(path),
+                    _ => asset_paths(value, paths),
+                }
+            }
+        }
+        serde_yaml::Value::Sequence(sequence) => {
+            for value in sequence {
+                asset_paths(value, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns a hash of everything determining the samples of a render apart from the seed: the super sampling,
+/// filter, sampler, adaptive sampling, whether the render is progressive and the integrator with its parameters.
+/// The stop conditions of progressive renders are left out, so a resumed render may use a higher target.
+///
+/// # Arguments
+///
+/// * `settings` settings of the render
+/// * `integrator` light transport algorithm of the render
+pub fn settings_hash(settings: &RenderSettings, integrator: &dyn Integrator) -> u64 {
+    let description = format!(
+        "{:?} {:?} {:?} {:?} {} {:?}",
+        settings.ssaa,
+        settings.filter,
+        settings.sampler,
+        settings.adaptive,
+        settings.progressive.is_some(),
+        integrator
+    );
+    content_hash(description.as_bytes())
+}
+
+/// Returns a hash of a sequence of bytes, which is stable across runs and platforms
+fn content_hash(bytes: &[u8]) -> u64 {
+    bytes
+        .chunks(8)
+        .fold(hash(&[bytes.len() as u64]), |h, chunk| {
+            let mut chunk_bytes = [0u8; 8];
+            chunk_bytes[..chunk.len()].copy_from_slice(chunk);
+            hash(&[h, u64::from_le_bytes(chunk_bytes)])
+        })
+}
+
+fn write_u64(writer: &mut impl Write, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_f64(writer: &mut impl Write, value: f64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64(reader: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::raytracer::{adaptive::PixelStatistics, film::FilmPixel, image::Color};
+
+    use super::{content_hash, scene_hash, CameraCheckpoint, Checkpoint};
+
+    #[test]
+    fn test_write_read() {
+        let checkpoint = Checkpoint {
+            scene_hash: scene_hash(b"width: 2\nheight: 1\n").unwrap(),
+            settings_hash: 7,
+            seed: 42,
+            passes: 3,
+            cameras: vec![CameraCheckpoint {
+                next_tile: 1,
+                film: vec![
+                    FilmPixel {
+                        color: Color::new(0.25, 0.5, 1.5),
+                        weight: 3.0,
+                    },
+                    FilmPixel {
+                        color: Color::new(0.0, 0.0, 0.0),
+                        weight: 0.0,
+                    },
+                ],
+                statistics: vec![
+                    PixelStatistics {
+                        count: 3,
+                        mean: 0.5,
+                        squared_deviations: 0.125,
+                    },
+                    PixelStatistics::default(),
+                ],
+            }],
+        };
+        let path = std::env::temp_dir().join(format!(
+            "raytracer-checkpoint-test-{}.checkpoint",
+            std::process::id()
+        ));
+        checkpoint.write(&path).unwrap();
+        let read = Checkpoint::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, checkpoint);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(content_hash(b"width: 10"), content_hash(b"width: 10"));
+        assert_ne!(content_hash(b"width: 10"), content_hash(b"width: 11"));
+        // the length is part of the hash, so zero padding doesn't collide
+        assert_ne!(content_hash(b"a"), content_hash(b"a\0"));
+    }
+
+    #[test]
+    fn test_scene_hash() {
+        assert_eq!(
+            scene_hash(b"width: 10").unwrap(),
+            scene_hash(b"width: 10").unwrap()
+        );
+        assert_ne!(
+            scene_hash(b"width: 10").unwrap(),
+            scene_hash(b"width: 11").unwrap()
+        );
+
+        // edits of referenced files change the hash even though the scene file stays the same
+        let asset_path = std::env::temp_dir().join(format!(
+            "raytracer-checkpoint-test-{}.obj",
+            std::process::id()
+        ));
+        let scene = format!(
+            "objects:\n  - type: Mesh\n    materials:\n      default:\n        type: Texture\n        texture_path: {}\n",
+            asset_path.display()
+        );
+        std::fs::write(&asset_path, "v 0 0 0").unwrap();
+        let first = scene_hash(scene.as_bytes()).unwrap();
+        std::fs::write(&asset_path, "v 0 0 1").unwrap();
+        let edited = scene_hash(scene.as_bytes()).unwrap();
+        std::fs::remove_file(&asset_path).unwrap();
+        assert_ne!(first, edited);
+        assert!(scene_hash(scene.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_path() {
+        assert_eq!(
+            Checkpoint::path(Path::new("renders/out.png")),
+            Path::new("renders/out.checkpoint")
+        );
+    }
+}
@@ -0,0 +1,398 @@
+use crate::raytracer::image::Color;
+
+/// Reconstruction filter weighting the samples around a pixel center by their distance.
+/// Each variant holds the radius of the filter in pixels, samples further away in either direction are ignored.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Filter {
+    /// Constant weight over the whole radius, with a radius of `0.5` each sample only contributes to its own pixel
+    Box(f64),
+    /// Weight falling off linearly towards the radius
+    Tent(f64),
+    /// Gaussian falloff which is shifted to reach zero at the radius
+    Gaussian(f64),
+    /// Mitchell-Netravali cubic with `B = C = 1/3`
+    Mitchell(f64),
+    /// Sinc windowed by a sinc stretched to the radius
+    Lanczos(f64),
+}
+
+impl Filter {
+    /// Returns the radius of the filter in pixels
+    pub fn radius(&self) -> f64 {
+        match self {
+            Filter::Box(radius)
+            | Filter::Tent(radius)
+            | Filter::Gaussian(radius)
+            | Filter::Mitchell(radius)
+            | Filter::Lanczos(radius) => *radius,
+        }
+    }
+
+    /// Returns the weight of a sample with the given offset to the pixel center
+    ///
+    /// # Arguments
+    ///
+    /// * `dx` offset along the x-axis in pixels
+    /// * `dy` offset along the y-axis in pixels
+    pub fn evaluate(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(&self, d: f64) -> f64 {
+        let radius = self.radius();
+        let d = d.abs();
+        if d > radius {
+            return 0.0;
+        }
+        match self {
+            Filter::Box(_) => 1.0,
+            Filter::Tent(_) => radius - d,
+            Filter::Gaussian(_) => {
+                let alpha = 2.0;
+                f64::exp(-alpha * d * d) - f64::exp(-alpha * radius * radius)
+            }
+            Filter::Mitchell(_) => mitchell(2.0 * d / radius),
+            Filter::Lanczos(_) => sinc(d) * sinc(d / radius),
+        }
+    }
+}
+
+/// Mitchell-Netravali cubic with `B = C = 1/3` defined on `[0, 2]`
+fn mitchell(x: f64) -> f64 {
+    let b = 1.0 / 3.0;
+    let c = 1.0 / 3.0;
+    if x > 1.0 {
+        ((-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c))
+            / 6.0
+    } else {
+        ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b))
+            / 6.0
+    }
+}
+
+/// Normalized sinc function `sin(pi x) / (pi x)`
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+    let pi_x = std::f64::consts::PI * x;
+    pi_x.sin() / pi_x
+}
+
+/// Error Type for Decoding a Filter-Variant from a String
+pub struct FilterDecodeError {
+    error: String,
+}
+
+impl FilterDecodeError {
+    fn new(error: String) -> FilterDecodeError {
+        FilterDecodeError { error }
+    }
+}
+
+impl std::error::Error for FilterDecodeError {}
+
+impl std::fmt::Display for FilterDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing filter argument: {}", self.error)
+    }
+}
+
+impl std::fmt::Debug for FilterDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing filter argument: {}", self.error)
+    }
+}
+
+impl std::str::FromStr for Filter {
+    type Err = FilterDecodeError;
+
+    fn from_str(s: &str) -> Result<Filter, FilterDecodeError> {
+        let method_args = s.split(':').collect::<Vec<_>>();
+        let filter: fn(f64) -> Filter = match method_args[0] {
+            "box" => Filter::Box,
+            "tent" => Filter::Tent,
+            "gaussian" => Filter::Gaussian,
+            "mitchell" => Filter::Mitchell,
+            "lanczos" => Filter::Lanczos,
+            _ => return Err(FilterDecodeError::new("unknown filter".to_string())),
+        };
+        if method_args.len() < 2 {
+            return Ok(filter(default_radius(method_args[0])));
+        }
+        match method_args[1].parse::<f64>() {
+            Ok(radius) if radius > 0.0 => Ok(filter(radius)),
+            _ => Err(FilterDecodeError::new(
+                "radius has to be a positive number".to_string(),
+            )),
+        }
+    }
+}
+
+/// Returns the radius used for a filter if none is given
+fn default_radius(name: &str) -> f64 {
+    match name {
+        "box" => 0.5,
+        "tent" => 1.0,
+        "gaussian" => 1.5,
+        "mitchell" => 2.0,
+        _ => 3.0,
+    }
+}
+
+/// Weighted sum of the samples splatted into a pixel
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FilmPixel {
+    /// sum of the weighted radiance of the samples
+    pub color: Color,
+    /// sum of the weights of the samples
+    pub weight: f64,
+}
+
+/// Film collecting the radiance samples of the image in a flat framebuffer.
+/// Every sample is splatted into all pixels whose center lies within the radius of the filter and weighted by it,
+/// the color of a pixel is the weighted average of its samples.
+/// A film can also cover only a region of the image (see [for_tile](Self::for_tile)), which is merged into the film
+/// of the whole image afterwards.
+pub struct Film {
+    /// coordinate of the lower left pixel of the covered region on the x-axis
+    x: usize,
+    /// coordinate of the lower left pixel of the covered region on the y-axis
+    y: usize,
+    width: usize,
+    height: usize,
+    filter: Filter,
+    /// pixels in row -> column order, starting with the bottom row
+    pixels: Vec<FilmPixel>,
+}
+
+impl Film {
+    /// Creates an empty film for an image of the given size
+    ///
+    /// # Arguments
+    ///
+    /// * `width` width of the image
+    /// * `height` height of the image
+    /// * `filter` reconstruction filter used to weight the samples
+    pub fn new(width: usize, height: usize, filter: Filter) -> Film {
+        let empty = FilmPixel {
+            color: Color::new(0.0, 0.0, 0.0),
+            weight: 0.0,
+        };
+        Film {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            filter,
+            pixels: vec![empty; width * height],
+        }
+    }
+
+    /// Creates an empty film for a tile of the image covered by this film, padded by the pixels around the tile which
+    /// the filter reaches from samples inside of it. Samples of the tile can be added to the returned film on another
+    /// thread and are [merged](Self::merge) into this film afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` coordinate of the lower left pixel of the tile on the x-axis
+    /// * `y` coordinate of the lower left pixel of the tile on the y-axis
+    /// * `width` width of the tile
+    /// * `height` height of the tile
+    pub fn for_tile(&self, x: usize, y: usize, width: usize, height: usize) -> Film {
+        let padding = self.filter.radius().ceil() as usize + 1;
+        let min_x = x.saturating_sub(padding).max(self.x);
+        let min_y = y.saturating_sub(padding).max(self.y);
+        let max_x = (x + width + padding).min(self.x + self.width);
+        let max_y = (y + height + padding).min(self.y + self.height);
+        Film {
+            x: min_x,
+            y: min_y,
+            ..Film::new(max_x - min_x, max_y - min_y, self.filter)
+        }
+    }
+
+    /// Adds a sample at the given image position to all pixels covered by the filter
+    ///
+    /// # Arguments
+    ///
+    /// * `x` position of the sample on the x-axis
+    /// * `y` position of the sample on the y-axis
+    /// * `color` radiance of the sample
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Color) {
+        let radius = self.filter.radius();
+        // position relative to the covered region
+        let x = x - self.x as f64;
+        let y = y - self.y as f64;
+        // pixels whose footprint [center - radius, center + radius) contains the sample
+        let x_range = pixel_range(x, radius, self.width);
+        for j in pixel_range(y, radius, self.height) {
+            let row = &mut self.pixels[j * self.width..(j + 1) * self.width];
+            for i in x_range.clone() {
+                let weight = self
+                    .filter
+                    .evaluate(x - (i as f64 + 0.5), y - (j as f64 + 0.5));
+                if weight != 0.0 {
+                    row[i].color += color * weight;
+                    row[i].weight += weight;
+                }
+            }
+        }
+    }
+
+    /// Adds the weighted sums of a film created by [for_tile](Self::for_tile) to the pixels it covers
+    ///
+    /// # Arguments
+    ///
+    /// * `tile` film of a tile of the region covered by this film
+    pub fn merge(&mut self, tile: &Film) {
+        for (j, row) in tile.pixels.chunks(tile.width).enumerate() {
+            let start = (tile.y + j - self.y) * self.width + tile.x - self.x;
+            for (pixel, tile_pixel) in self.pixels[start..start + tile.width].iter_mut().zip(row) {
+                pixel.color += tile_pixel.color;
+                pixel.weight += tile_pixel.weight;
+            }
+        }
+    }
+
+    /// Returns the weighted sums of the pixels in row -> column order, starting with the bottom row
+    pub fn pixels(&self) -> &[FilmPixel] {
+        &self.pixels
+    }
+
+    /// Replaces the weighted sums of all pixels, e.g. to continue a render from a checkpoint.
+    /// Returns `false` and leaves the film untouched if the number of pixels doesn't match.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` weighted sums in the order returned by [pixels](Self::pixels)
+    pub fn restore(&mut self, pixels: Vec<FilmPixel>) -> bool {
+        if pixels.len() != self.pixels.len() {
+            return false;
+        }
+        self.pixels = pixels;
+        true
+    }
+
+    /// Returns the reconstructed pixel colors in row -> column order, starting with the top row.
+    /// Samples can still be added afterwards, e.g. to refine the image of a progressive render.
+    pub fn pixel_colors(&self) -> Vec<Color> {
+        self.pixels
+            .chunks(self.width)
+            .rev()
+            .flatten()
+            .map(|pixel| {
+                if pixel.weight > 0.0 {
+                    pixel.color * (1.0 / pixel.weight)
+                } else {
+                    Color::new(0.0, 0.0, 0.0)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the indices of the pixels along one axis whose footprint contains the sample
+fn pixel_range(position: f64, radius: f64, size: usize) -> std::ops::Range<usize> {
+    let min = (f64::floor(position - 0.5 - radius) + 1.0).max(0.0) as usize;
+    let max = (f64::floor(position - 0.5 + radius) + 1.0).clamp(0.0, size as f64) as usize;
+    min..max.max(min)
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use crate::raytracer::image::Color;
+
+    use super::{Film, Filter};
+
+    #[test]
+    fn test_box_filter_single_pixel() {
+        let mut film = Film::new(3, 2, Filter::Box(0.5));
+        film.add_sample(1.0, 0.0, Color::new(1.0, 0.0, 0.0));
+        film.add_sample(1.5, 0.5, Color::new(0.0, 1.0, 0.0));
+
+        let pixels = film.pixel_colors();
+        // the bottom row is returned last
+        assert_eq!(pixels[4], Color::new(0.5, 0.5, 0.0));
+        assert_eq!(pixels[3], Color::new(0.0, 0.0, 0.0));
+        assert_eq!(pixels[1], Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_filter_splats_into_neighbours() {
+        let mut film = Film::new(3, 3, Filter::Tent(1.0));
+        film.add_sample(1.5, 1.5, Color::new(1.0, 1.0, 1.0));
+        film.add_sample(1.0, 1.5, Color::new(0.0, 0.0, 0.0));
+
+        let pixels = film.pixel_colors();
+        // both samples are equally far from the center pixel, the left one only reaches the left neighbour
+        assert_eq!(pixels[4], Color::new(2.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0));
+        assert_eq!(pixels[3], Color::new(0.0, 0.0, 0.0));
+        assert_eq!(pixels[5], Color::new(0.0, 0.0, 0.0));
+        assert_eq!(pixels[0], Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_merge_tiles() {
+        let filter = Filter::Mitchell(2.0);
+        let mut film = Film::new(7, 5, filter);
+        let mut merged = Film::new(7, 5, filter);
+        for (x, y, width, height) in [(0, 0, 4, 3), (4, 0, 3, 3), (0, 3, 4, 2), (4, 3, 3, 2)] {
+            let mut tile = merged.for_tile(x, y, width, height);
+            for j in y..y + height {
+                for i in x..x + width {
+                    let (sx, sy) = (i as f64 + 0.25, j as f64 + 0.75);
+                    let color = Color::new(i as f64, j as f64, 1.0);
+                    film.add_sample(sx, sy, color);
+                    tile.add_sample(sx, sy, color);
+                }
+            }
+            merged.merge(&tile);
+        }
+
+        for (pixel, merged_pixel) in film.pixels().iter().zip(merged.pixels()) {
+            assert!((pixel.weight - merged_pixel.weight).abs() < 1e-12);
+            assert!((pixel.color.r - merged_pixel.color.r).abs() < 1e-12);
+            assert!((pixel.color.g - merged_pixel.color.g).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_filter_weights() {
+        for filter in [
+            Filter::Box(1.5),
+            Filter::Tent(1.5),
+            Filter::Gaussian(1.5),
+            Filter::Mitchell(1.5),
+            Filter::Lanczos(1.5),
+        ] {
+            assert!(filter.evaluate(0.0, 0.0) > 0.0);
+            assert_eq!(filter.evaluate(1.6, 0.0), 0.0);
+            assert_eq!(filter.evaluate(0.3, -0.2), filter.evaluate(-0.3, 0.2));
+        }
+        assert!(Filter::Gaussian(1.5).evaluate(1.5, 0.0).abs() < 1e-12);
+        assert!(Filter::Mitchell(2.0).evaluate(2.0, 0.0).abs() < 1e-12);
+        // the lobes of mitchell and lanczos become negative
+        assert!(Filter::Mitchell(2.0).evaluate(1.5, 0.0) < 0.0);
+        assert!(Filter::Lanczos(3.0).evaluate(1.5, 0.0) < 0.0);
+    }
+
+    #[test]
+    fn test_filter_from_str() {
+        assert_eq!(Filter::from_str("box").unwrap(), Filter::Box(0.5));
+        assert_eq!(Filter::from_str("mitchell").unwrap(), Filter::Mitchell(2.0));
+        assert_eq!(
+            Filter::from_str("gaussian:2.5").unwrap(),
+            Filter::Gaussian(2.5)
+        );
+        assert!(Filter::from_str("lanczos:-1").is_err());
+        assert!(Filter::from_str("triangle").is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Deserializer};
 use std::fs;
-use std::io::BufWriter;
+use std::io::{BufRead, BufReader, BufWriter, Read};
 use std::ops::{Add, AddAssign, DivAssign, Mul};
 
 use crate::math::Vector3;
@@ -25,6 +25,16 @@ impl Color {
         Color { r, g, b }
     }
 
+    /// Checks if all channels of the color are zero
+    pub fn is_black(&self) -> bool {
+        self.r == 0.0 && self.g == 0.0 && self.b == 0.0
+    }
+
+    /// Returns the largest value of the three channels
+    pub fn max_channel(&self) -> f64 {
+        self.r.max(self.g).max(self.b)
+    }
+
     /// Clamps the color to a maximum of `1.0` to avoid over-saturation
     pub fn clamp(&mut self) {
         self.r = f64::min(self.r, 1.0);
@@ -34,7 +44,7 @@ impl Color {
 
     /// Converts the current value to PPM compatible output values contained in an integer array.
     #[inline]
-    pub fn to_output(&self) -> [u8; 3] {
+    pub fn to_output(self) -> [u8; 3] {
         [
             (255.999 * self.r) as u8,
             (255.999 * self.g) as u8,
@@ -42,6 +52,24 @@ impl Color {
         ]
     }
 
+    /// Decodes a pixel of the shared exponent RGBE format used by Radiance `.hdr` files
+    pub fn from_rgbe(rgbe: [u8; 4]) -> Color {
+        if rgbe[3] == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let scale = 2f64.powi(rgbe[3] as i32 - (128 + 8));
+        Color::new(
+            rgbe[0] as f64 * scale,
+            rgbe[1] as f64 * scale,
+            rgbe[2] as f64 * scale,
+        )
+    }
+
+    /// Returns the luminance of the (linear) color
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
     pub fn from_output(bytes: [u8; 3]) -> Color {
         Color::new(
             bytes[0] as f64 / 255.999,
@@ -118,27 +146,62 @@ impl PartialEq<Color> for Color {
     fn eq(&self, other: &Color) -> bool {
         self.r == other.r && self.g == other.g && self.b == other.b
     }
-
-    fn ne(&self, other: &Color) -> bool {
-        !(self == other)
-    }
 }
 
 #[inline]
-fn to_u8_buf(pixel_colors: Vec<Vec<Color>>) -> Box<[u8]> {
-    let result: Vec<u8> = pixel_colors
-        .iter()
-        .flatten()
-        .flat_map(|&c| c.to_output())
-        .collect();
+fn to_u8_buf(pixel_colors: &[Color]) -> Box<[u8]> {
+    let result: Vec<u8> = pixel_colors.iter().flat_map(|&c| c.to_output()).collect();
     result.into_boxed_slice()
 }
 
+/// Joins two images of equal size horizontally with `left` on the left half of the resulting image
+///
+/// # Arguments
+///
+/// * `left` pixels of the left image in row -> column order
+/// * `right` pixels of the right image in row -> column order
+/// * `width` width of each of the images
+pub fn join_side_by_side<T: Clone>(left: &[T], right: &[T], width: usize) -> Vec<T> {
+    left.chunks(width)
+        .zip(right.chunks(width))
+        .flat_map(|(left_row, right_row)| left_row.iter().chain(right_row).cloned())
+        .collect()
+}
+
+/// Joins two images of equal size vertically with `top` on the upper half of the resulting image
+///
+/// # Arguments
+///
+/// * `top` pixels of the upper image in row -> column order
+/// * `bottom` pixels of the lower image in row -> column order
+pub fn join_over_under<T>(mut top: Vec<T>, bottom: Vec<T>) -> Vec<T> {
+    top.extend(bottom);
+    top
+}
+
+/// Returns the path of an additional output written next to an image, e.g. `out_normal.png` for `out.png`
+///
+/// # Arguments
+///
+/// * `output_path` Path of the image
+/// * `suffix` suffix appended to the file name of the image
+pub fn suffixed_path(output_path: &std::path::Path, suffix: &str) -> std::path::PathBuf {
+    let stem = output_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut file_name = format!("{}_{}", stem, suffix);
+    if let Some(extension) = output_path.extension() {
+        file_name = format!("{}.{}", file_name, extension.to_string_lossy());
+    }
+    output_path.with_file_name(file_name)
+}
+
 /// Writes the current Image data (Pixel colors) to a png file at the given `output_path`.
 ///
 /// # Arguments
 ///
-/// * `pixel_colors` Vector containing the colors in row -> column order (size: width * height)
+/// * `pixel_colors` colors in row -> column order starting with the top row (size: width * height)
 /// * `width` width of the image
 /// * `height` height of the image
 /// * `output_path` Path specifying the output file to write to (will be created if it doesn't exist and overriden if it exists)
@@ -147,7 +210,7 @@ fn to_u8_buf(pixel_colors: Vec<Vec<Color>>) -> Box<[u8]> {
 ///
 /// If the file cannot be written to.
 pub fn write_image(
-    pixel_colors: Vec<Vec<Color>>,
+    pixel_colors: &[Color],
     width: usize,
     height: usize,
     output_path: &std::path::Path,
@@ -155,11 +218,11 @@ pub fn write_image(
     let parent_dir = output_path.parent().unwrap();
     fs::create_dir_all(parent_dir).unwrap();
     let file = fs::File::create(output_path).unwrap();
-    let ref mut w = BufWriter::new(file);
+    let w = &mut BufWriter::new(file);
     let mut encoder = png::Encoder::new(w, width as u32, height as u32);
     encoder.set_color(png::ColorType::Rgb);
     let mut writer = encoder.write_header().unwrap();
-    writer.write_image_data(&*to_u8_buf(pixel_colors)).unwrap();
+    writer.write_image_data(&to_u8_buf(pixel_colors)).unwrap();
 }
 
 /// Reads and Image
@@ -182,3 +245,166 @@ pub fn read_image(file_path: &std::path::Path) -> (Vec<Color>, usize, usize) {
     }
     (result, info.width as usize, info.height as usize)
 }
+
+/// Reads a Radiance `.hdr` image containing linear colors in row -> column order.
+///
+/// # Arguments
+///
+/// * `file_path` path of the image to read
+///
+/// # Panics
+///
+/// If the file cannot be read or is no valid Radiance image with the standard `-Y height +X width` orientation.
+pub fn read_hdr_image(file_path: &std::path::Path) -> (Vec<Color>, usize, usize) {
+    let file = fs::File::open(file_path).unwrap();
+    decode_hdr(BufReader::new(file))
+}
+
+fn decode_hdr<R: BufRead>(mut reader: R) -> (Vec<Color>, usize, usize) {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    if !line.starts_with("#?") {
+        panic!("Missing Radiance header");
+    }
+    loop {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        let header = line.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(format) = header.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                panic!("Unsupported Radiance format {}", format);
+            }
+        }
+    }
+    line.clear();
+    reader.read_line(&mut line).unwrap();
+    let resolution: Vec<&str> = line.split_whitespace().collect();
+    let (height, width) = match resolution[..] {
+        ["-Y", height, "+X", width] => (height.parse().unwrap(), width.parse().unwrap()),
+        _ => panic!("Unsupported Radiance resolution {}", line.trim()),
+    };
+
+    let mut result = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        read_hdr_scanline(&mut reader, &mut scanline);
+        result.extend(scanline.iter().map(|&rgbe| Color::from_rgbe(rgbe)));
+    }
+    (result, width, height)
+}
+
+/// Reads a single scanline, which is either stored as flat RGBE pixels or run length encoded per channel
+fn read_hdr_scanline<R: Read>(reader: &mut R, scanline: &mut [[u8; 4]]) {
+    let width = scanline.len();
+    let mut start = [0u8; 4];
+    reader.read_exact(&mut start).unwrap();
+    let is_rle = (8..0x8000).contains(&width)
+        && start[0] == 2
+        && start[1] == 2
+        && (start[2] as usize) << 8 | start[3] as usize == width;
+    if !is_rle {
+        scanline[0] = start;
+        for pixel in scanline.iter_mut().skip(1) {
+            reader.read_exact(pixel).unwrap();
+        }
+        return;
+    }
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0u8; 2];
+            reader.read_exact(&mut count[..1]).unwrap();
+            if count[0] > 128 {
+                let run = (count[0] - 128) as usize;
+                reader.read_exact(&mut count[1..]).unwrap();
+                for pixel in &mut scanline[x..x + run] {
+                    pixel[channel] = count[1];
+                }
+                x += run;
+            } else {
+                for pixel in &mut scanline[x..x + count[0] as usize] {
+                    let mut value = [0u8];
+                    reader.read_exact(&mut value).unwrap();
+                    pixel[channel] = value[0];
+                }
+                x += count[0] as usize;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_hdr, join_over_under, join_side_by_side, Color};
+
+    #[test]
+    fn test_from_rgbe() {
+        assert_eq!(Color::from_rgbe([0, 0, 0, 0]), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(
+            Color::from_rgbe([128, 64, 0, 129]),
+            Color::new(1.0, 0.5, 0.0)
+        );
+        assert_eq!(
+            Color::from_rgbe([128, 0, 0, 131]),
+            Color::new(4.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_decode_flat_hdr() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 2\n".to_vec();
+        bytes.extend([128, 0, 0, 129, 0, 128, 0, 129, 0, 0, 128, 129, 0, 0, 0, 0]);
+
+        let (pixels, width, height) = decode_hdr(&bytes[..]);
+        assert_eq!((width, height), (2, 2));
+        assert_eq!(
+            pixels,
+            vec![
+                Color::new(1.0, 0.0, 0.0),
+                Color::new(0.0, 1.0, 0.0),
+                Color::new(0.0, 0.0, 1.0),
+                Color::new(0.0, 0.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_rle_hdr() {
+        let mut bytes = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+        bytes.extend([2, 2, 0, 8]);
+        // red: run of 8
+        bytes.extend([128 + 8, 128]);
+        // green: 4 literals followed by a run of 4
+        bytes.extend([4, 0, 64, 128, 0, 128 + 4, 0]);
+        // blue: run of 8
+        bytes.extend([128 + 8, 0]);
+        // exponent: run of 8
+        bytes.extend([128 + 8, 129]);
+
+        let (pixels, width, height) = decode_hdr(&bytes[..]);
+        assert_eq!((width, height), (8, 1));
+        assert!(pixels.iter().all(|p| p.r == 1.0 && p.b == 0.0));
+        let green: Vec<f64> = pixels.iter().map(|p| p.g).collect();
+        assert_eq!(green, vec![0.0, 0.5, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_join_stereo_images() {
+        let left = vec![Color::new(1.0, 0.0, 0.0); 2 * 3];
+        let right = vec![Color::new(0.0, 0.0, 1.0); 2 * 3];
+
+        let side_by_side = join_side_by_side(&left, &right, 2);
+        assert_eq!(side_by_side.len(), 12);
+        assert_eq!(side_by_side[4 + 1], left[0]);
+        assert_eq!(side_by_side[4 + 2], right[0]);
+        assert_eq!(side_by_side[8 + 3], right[0]);
+
+        let over_under = join_over_under(left.clone(), right.clone());
+        assert_eq!(over_under.len(), 12);
+        assert_eq!(over_under[2 * 2], left[0]);
+        assert_eq!(over_under[3 * 2], right[0]);
+    }
+}
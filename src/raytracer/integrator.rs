@@ -0,0 +1,746 @@
+use crate::raytracer::image::Color;
+use crate::raytracer::raytrace::Ray;
+use crate::raytracer::sampler::Sampler;
+use crate::raytracer::scene::{
+    intersections::IntersectionInfo,
+    lights::{power_heuristic, LightSample},
+    materials::{BsdfSample, Material, Scatter},
+    Scene,
+};
+
+/// Light transport algorithm which computes the radiance arriving at the camera along a primary ray.
+/// The debug representation has to include all parameters, it identifies the integrator of a checkpoint.
+pub trait Integrator: Sync + std::fmt::Debug {
+    /// Returns the radiance arriving along the inverse direction of `ray`
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` The current scene
+    /// * `ray` primary ray spawned by the camera
+    /// * `sampler` sampler providing all random numbers of the path, already started for the current pixel sample
+    fn radiance(&self, scene: &Scene, ray: Ray, sampler: &mut dyn Sampler) -> Color;
+}
+
+/// Uniform random numbers of one bounce along a path.
+/// They are always drawn in the same order and amount, whether they are used or not,
+/// so every bounce of every path uses the same dimensions of the sampler.
+struct BounceSample {
+    light: f64,
+    light_direction: (f64, f64),
+    bsdf: BsdfSample,
+    russian_roulette: f64,
+}
+
+impl BounceSample {
+    fn new(sampler: &mut dyn Sampler) -> BounceSample {
+        BounceSample {
+            light: sampler.get_1d(),
+            light_direction: sampler.get_2d(),
+            bsdf: BsdfSample::new(sampler),
+            russian_roulette: sampler.get_1d(),
+        }
+    }
+}
+
+/// Enum representing the integrators which can be selected on the command line
+#[derive(Debug, PartialEq)]
+pub enum IntegratorType {
+    /// Unidirectional path tracing with next event estimation
+    Path,
+    /// Direct lighting only, i.e. light reaching the camera after a single diffuse bounce
+    Direct,
+    /// Classic Whitted ray tracing following only specular reflection and refraction
+    Whitted,
+    /// Ambient occlusion with the maximum distance of occluding objects
+    AmbientOcclusion(f64),
+}
+
+impl IntegratorType {
+    /// Creates the integrator of this type
+    ///
+    /// # Arguments
+    ///
+    /// * `max_depth` maximum number of ray bounces
+    /// * `rr_depth` number of bounces after which paths are terminated by Russian roulette
+    pub fn build(&self, max_depth: u32, rr_depth: u32) -> Box<dyn Integrator> {
+        match self {
+            IntegratorType::Path => Box::new(PathIntegrator {
+                max_depth,
+                rr_depth,
+            }),
+            IntegratorType::Direct => Box::new(DirectLightingIntegrator { max_depth }),
+            IntegratorType::Whitted => Box::new(WhittedIntegrator { max_depth }),
+            IntegratorType::AmbientOcclusion(distance) => Box::new(AmbientOcclusionIntegrator {
+                distance: *distance,
+            }),
+        }
+    }
+}
+
+/// Error Type for Decoding an IntegratorType-Variant from a String
+pub struct IntegratorDecodeError {
+    error: String,
+}
+
+impl IntegratorDecodeError {
+    fn new(error: String) -> IntegratorDecodeError {
+        IntegratorDecodeError { error }
+    }
+}
+
+impl std::error::Error for IntegratorDecodeError {}
+
+impl std::fmt::Display for IntegratorDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing integrator argument: {}", self.error)
+    }
+}
+
+impl std::fmt::Debug for IntegratorDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing integrator argument: {}", self.error)
+    }
+}
+
+impl std::str::FromStr for IntegratorType {
+    type Err = IntegratorDecodeError;
+
+    fn from_str(s: &str) -> Result<IntegratorType, IntegratorDecodeError> {
+        let method_args = s.split(':').collect::<Vec<_>>();
+        match method_args[0] {
+            "path" => Ok(IntegratorType::Path),
+            "direct" => Ok(IntegratorType::Direct),
+            "whitted" => Ok(IntegratorType::Whitted),
+            "ao" => {
+                if method_args.len() < 2 {
+                    return Ok(IntegratorType::AmbientOcclusion(f64::INFINITY));
+                }
+                match method_args[1].parse::<f64>() {
+                    Ok(distance) if distance > 0.0 => {
+                        Ok(IntegratorType::AmbientOcclusion(distance))
+                    }
+                    _ => Err(IntegratorDecodeError::new(
+                        "distance has to be a positive number".to_string(),
+                    )),
+                }
+            }
+            _ => Err(IntegratorDecodeError::new("unknown integrator".to_string())),
+        }
+    }
+}
+
+/// Path tracer sampling a light explicitly at every intersection with a non-specular material (next event estimation)
+/// and combining it with the emission found by the scattered ray using multiple importance sampling.
+/// The ray scattered at the last of the `max_depth` bounces only gathers emission.
+/// After `rr_depth` bounces paths are terminated randomly by Russian roulette based on their throughput,
+/// which keeps the estimate unbiased while most of the work is spent on paths that still contribute.
+#[derive(Debug)]
+pub struct PathIntegrator {
+    pub max_depth: u32,
+    pub rr_depth: u32,
+}
+
+impl Integrator for PathIntegrator {
+    fn radiance(&self, scene: &Scene, ray: Ray, sampler: &mut dyn Sampler) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = ray;
+        // pdf with which the previous intersection scattered the ray or `None` for primary rays
+        // and rays scattered by specular materials, whose emission hits are not weighted
+        let mut scatter_pdf: Option<f64> = None;
+
+        // the ray scattered at the last bounce is still traced to gather the emission it finds,
+        // otherwise the part of the direct lighting weighted towards it would be missing
+        for depth in 0..=self.max_depth {
+            let sample = BounceSample::new(sampler);
+            let intersection_info = match scene.get_closest_interesection(&ray) {
+                Some(info) => info,
+                None => {
+                    color += throughput * background(scene, &ray, scatter_pdf);
+                    break;
+                }
+            };
+            let material = intersection_info.material;
+            color += throughput * emission(scene, &ray, &intersection_info, scatter_pdf);
+            if depth == self.max_depth {
+                break;
+            }
+
+            let (scattered, albedo) = match material.scatter(&ray, &intersection_info, sample.bsdf)
+            {
+                Some((Some(scattered), albedo)) => (scattered, albedo),
+                Some((None, albedo)) => {
+                    color += throughput * unscattered(material, albedo);
+                    break;
+                }
+                None => break,
+            };
+
+            scatter_pdf = None;
+            if !material.is_specular() {
+                color += throughput * sample_direct_light(scene, &ray, &intersection_info, &sample);
+                scatter_pdf = Some(material.pdf(&ray, &intersection_info, &scattered.direction));
+            }
+            throughput =
+                match self.russian_roulette(depth, throughput * albedo, sample.russian_roulette) {
+                    Some(throughput) => throughput,
+                    None => break,
+                };
+            ray = scattered;
+        }
+        color
+    }
+}
+
+impl PathIntegrator {
+    /// Decides whether a path continues after the bounce at `depth`.
+    /// Returns the throughput of the surviving path, which is divided by the survival probability once
+    /// Russian roulette applies, or `None` if the path is terminated.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` number of the current bounce, starting with `0` for the primary ray
+    /// * `throughput` throughput of the path including the current bounce
+    /// * `u` uniform random number deciding the survival
+    fn russian_roulette(&self, depth: u32, throughput: Color, u: f64) -> Option<Color> {
+        if depth + 1 < self.rr_depth {
+            return Some(throughput);
+        }
+        let survival = throughput.max_channel().min(1.0);
+        if survival <= 0.0 || u >= survival {
+            return None;
+        }
+        Some(throughput * (1.0 / survival))
+    }
+}
+
+/// Computes only direct lighting: at the first non-specular intersection a light is sampled explicitly and
+/// combined with the emission found by a single scattered ray using multiple importance sampling.
+/// Specular reflection and refraction are followed up to `max_depth` bounces so lights stay visible in mirrors and glass.
+#[derive(Debug)]
+pub struct DirectLightingIntegrator {
+    pub max_depth: u32,
+}
+
+impl Integrator for DirectLightingIntegrator {
+    fn radiance(&self, scene: &Scene, ray: Ray, sampler: &mut dyn Sampler) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = ray;
+        let mut scatter_pdf: Option<f64> = None;
+
+        for depth in 0..=self.max_depth {
+            let sample = BounceSample::new(sampler);
+            let intersection_info = match scene.get_closest_interesection(&ray) {
+                Some(info) => info,
+                None => {
+                    color += throughput * background(scene, &ray, scatter_pdf);
+                    break;
+                }
+            };
+            let material = intersection_info.material;
+            color += throughput * emission(scene, &ray, &intersection_info, scatter_pdf);
+            if scatter_pdf.is_some() || depth == self.max_depth {
+                // the scattered ray of the diffuse bounce only gathers emission, even beyond the last bounce
+                break;
+            }
+
+            let (scattered, albedo) = match material.scatter(&ray, &intersection_info, sample.bsdf)
+            {
+                Some((Some(scattered), albedo)) => (scattered, albedo),
+                Some((None, albedo)) => {
+                    color += throughput * unscattered(material, albedo);
+                    break;
+                }
+                None => break,
+            };
+            if !material.is_specular() {
+                color += throughput * sample_direct_light(scene, &ray, &intersection_info, &sample);
+                scatter_pdf = Some(material.pdf(&ray, &intersection_info, &scattered.direction));
+            }
+            throughput = throughput * albedo;
+            ray = scattered;
+        }
+        color
+    }
+}
+
+/// Classic Whitted ray tracing: non-specular surfaces are shaded by sampling every light of the scene once
+/// without any indirect illumination, while specular materials recursively follow their reflected or refracted ray.
+#[derive(Debug)]
+pub struct WhittedIntegrator {
+    pub max_depth: u32,
+}
+
+impl Integrator for WhittedIntegrator {
+    fn radiance(&self, scene: &Scene, ray: Ray, sampler: &mut dyn Sampler) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = ray;
+
+        for _ in 0..self.max_depth {
+            let sample = BounceSample::new(sampler);
+            let intersection_info = match scene.get_closest_interesection(&ray) {
+                Some(info) => info,
+                None => {
+                    color += throughput * scene.background_color(&ray);
+                    break;
+                }
+            };
+            let material = intersection_info.material;
+            color += throughput * material.emitted();
+
+            if !material.is_specular() {
+                for light in &scene.lights {
+                    // every light gets its own dimensions, so the samples of area and environment lights are independent
+                    let light_direction = sampler.get_2d();
+                    if let Some(light_sample) = light.sample(
+                        &scene.objects,
+                        &intersection_info.point,
+                        ray.time,
+                        light_direction,
+                    ) {
+                        color += throughput
+                            * light_contribution(scene, &ray, &intersection_info, &light_sample);
+                    }
+                }
+                break;
+            }
+
+            match material.scatter(&ray, &intersection_info, sample.bsdf) {
+                Some((Some(scattered), albedo)) => {
+                    throughput = throughput * albedo;
+                    ray = scattered;
+                }
+                Some((None, albedo)) => {
+                    color += throughput * unscattered(material, albedo);
+                    break;
+                }
+                None => break,
+            }
+        }
+        color
+    }
+}
+
+/// Ambient occlusion: returns the fraction of a cosine weighted hemisphere sample around the first intersection
+/// which isn't blocked by objects closer than `distance`. Rays missing the scene return the background.
+#[derive(Debug)]
+pub struct AmbientOcclusionIntegrator {
+    pub distance: f64,
+}
+
+impl Integrator for AmbientOcclusionIntegrator {
+    fn radiance(&self, scene: &Scene, ray: Ray, sampler: &mut dyn Sampler) -> Color {
+        let intersection_info = match scene.get_closest_interesection(&ray) {
+            Some(info) => info,
+            None => return scene.background_color(&ray),
+        };
+        let normal = intersection_info.facing_normal(&ray);
+        let mut direction = normal + crate::math::Vector3::sample_unit_vector(sampler.get_2d());
+        if direction.near_zero() {
+            direction = normal;
+        }
+        let occlusion_ray = Ray::with_time(intersection_info.point, direction, ray.time);
+        if scene.is_occluded(&occlusion_ray, self.distance) {
+            Color::new(0.0, 0.0, 0.0)
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+}
+
+/// Returns the radiance of a ray leaving the scene, weighted by multiple importance sampling against
+/// sampling the environment as a light if the ray was scattered by a non-specular material
+fn background(scene: &Scene, ray: &Ray, scatter_pdf: Option<f64>) -> Color {
+    let weight = match scatter_pdf {
+        Some(pdf) => power_heuristic(pdf, scene.environment_pdf(ray)),
+        None => 1.0,
+    };
+    scene.background_color(ray) * weight
+}
+
+/// Returns the emission of the intersected material, weighted by multiple importance sampling against
+/// sampling the light explicitly if the ray was scattered by a non-specular material
+fn emission(
+    scene: &Scene,
+    ray: &Ray,
+    intersection_info: &IntersectionInfo,
+    scatter_pdf: Option<f64>,
+) -> Color {
+    let emitted = intersection_info.material.emitted();
+    if emitted.is_black() {
+        return emitted;
+    }
+    let weight = match scatter_pdf {
+        Some(pdf) => power_heuristic(pdf, scene.light_pdf(ray, intersection_info)),
+        None => 1.0,
+    };
+    emitted * weight
+}
+
+/// Returns the color of a material which absorbed the ray without scattering it
+fn unscattered(material: &Material, albedo: Color) -> Color {
+    match material {
+        // emission is already accounted for separately
+        Material::Emissive(_) => Color::new(0.0, 0.0, 0.0),
+        _ => albedo,
+    }
+}
+
+/// Samples a light from the intersection point of the ray and returns its contribution
+/// weighted by multiple importance sampling against the material's own scattering.
+///
+/// # Arguments
+///
+/// * `scene` The current scene
+/// * `ray` the ray which hit the intersection
+/// * `intersection_info` intersection of the ray with a non-specular material
+/// * `sample` random numbers of the current bounce
+fn sample_direct_light(
+    scene: &Scene,
+    ray: &Ray,
+    intersection_info: &IntersectionInfo,
+    sample: &BounceSample,
+) -> Color {
+    let light_sample = match scene.sample_light(
+        &intersection_info.point,
+        ray.time,
+        sample.light,
+        sample.light_direction,
+    ) {
+        Some(sample) => sample,
+        None => return Color::new(0.0, 0.0, 0.0),
+    };
+    // delta lights can't be hit by scattered rays, so there is nothing to weight against
+    let weight = if light_sample.is_delta {
+        1.0
+    } else {
+        let scatter_pdf =
+            intersection_info
+                .material
+                .pdf(ray, intersection_info, &light_sample.direction);
+        power_heuristic(light_sample.pdf, scatter_pdf)
+    };
+    light_contribution(scene, ray, intersection_info, &light_sample) * weight
+}
+
+/// Returns the unweighted contribution of a light sample to the intersection point or black if the light is occluded
+fn light_contribution(
+    scene: &Scene,
+    ray: &Ray,
+    intersection_info: &IntersectionInfo,
+    light_sample: &LightSample,
+) -> Color {
+    let black = Color::new(0.0, 0.0, 0.0);
+    let bsdf = intersection_info
+        .material
+        .eval(ray, intersection_info, &light_sample.direction);
+    if bsdf.is_black() {
+        return black;
+    }
+
+    let shadow_ray = Ray::with_time(intersection_info.point, light_sample.direction, ray.time);
+    if scene.is_occluded(&shadow_ray, light_sample.distance * (1.0 - 1e-6)) {
+        return black;
+    }
+    bsdf * light_sample.emitted * (1.0 / light_sample.pdf)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        math::Vector3,
+        raytracer::{image::Color, raytrace::Ray, sampler::SamplerType, scene::Scene},
+    };
+
+    use super::{Integrator, IntegratorType, PathIntegrator};
+
+    #[test]
+    fn test_parse_integrator() {
+        assert_eq!(
+            "path".parse::<IntegratorType>().unwrap(),
+            IntegratorType::Path
+        );
+        assert_eq!(
+            "direct".parse::<IntegratorType>().unwrap(),
+            IntegratorType::Direct
+        );
+        assert_eq!(
+            "whitted".parse::<IntegratorType>().unwrap(),
+            IntegratorType::Whitted
+        );
+        assert_eq!(
+            "ao".parse::<IntegratorType>().unwrap(),
+            IntegratorType::AmbientOcclusion(f64::INFINITY)
+        );
+        assert_eq!(
+            "ao:2.5".parse::<IntegratorType>().unwrap(),
+            IntegratorType::AmbientOcclusion(2.5)
+        );
+        assert!("ao:-1".parse::<IntegratorType>().is_err());
+        assert!("bidir".parse::<IntegratorType>().is_err());
+    }
+
+    fn create_test_scene() -> Scene {
+        let yaml = "
+width: 10
+height: 10
+background: [0.5, 0.5, 0.5]
+camera:
+  eye: [0, 0, 5]
+  look_at: [0, 0, 0]
+  up: [0, 1, 0]
+  fovy: 45
+lights:
+  - type: Directional
+    direction: [0, -1, 0]
+    color: [1, 1, 1]
+objects:
+  - type: Plane
+    center: [0, 0, 0]
+    normal: [0, 1, 0]
+    material:
+      type: Lambertian
+      albedo: [0.5, 0.5, 0.5]
+  - type: Sphere
+    center: [0, 2, 0]
+    radius: 1
+    material:
+      type: Lambertian
+      albedo: [0.5, 0.5, 0.5]
+";
+        let mut scene: Scene = serde_yaml::from_str(yaml).unwrap();
+        scene.precompute();
+        scene
+    }
+
+    #[test]
+    fn test_ambient_occlusion() {
+        let scene = create_test_scene();
+        let below_sphere = Ray::new(Vector3::new(0.0, 5.0, 5.0), Vector3::new(0.0, -5.0, -5.0));
+        let open_floor = Ray::new(Vector3::new(50.0, 5.0, 5.0), Vector3::new(0.0, -5.0, -5.0));
+
+        let near = IntegratorType::AmbientOcclusion(0.5).build(1, 1);
+        let far = IntegratorType::AmbientOcclusion(f64::INFINITY).build(1, 1);
+        let mut sampler = SamplerType::Independent.build(0);
+        let mut occluded = 0;
+        for _ in 0..200 {
+            assert_eq!(
+                near.radiance(
+                    &scene,
+                    Ray::new(below_sphere.origin, below_sphere.direction),
+                    sampler.as_mut()
+                ),
+                Color::new(1.0, 1.0, 1.0)
+            );
+            assert_eq!(
+                far.radiance(
+                    &scene,
+                    Ray::new(open_floor.origin, open_floor.direction),
+                    sampler.as_mut()
+                ),
+                Color::new(1.0, 1.0, 1.0)
+            );
+            if far
+                .radiance(
+                    &scene,
+                    Ray::new(below_sphere.origin, below_sphere.direction),
+                    sampler.as_mut(),
+                )
+                .is_black()
+            {
+                occluded += 1;
+            }
+        }
+        assert!(occluded > 0);
+
+        let miss = Ray::new(Vector3::new(0.0, 5.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(
+            far.radiance(&scene, miss, sampler.as_mut()),
+            Color::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn test_whitted_shadow() {
+        let scene = create_test_scene();
+        let whitted = IntegratorType::Whitted.build(5, 5);
+        let mut sampler = SamplerType::Independent.build(0);
+
+        // the floor below the sphere is in its shadow and only the sky is seen along reflections
+        let shadowed = Ray::new(Vector3::new(0.0, 0.5, 5.0), Vector3::new(0.0, -0.5, -5.0));
+        assert!(whitted
+            .radiance(&scene, shadowed, sampler.as_mut())
+            .is_black());
+
+        let lit = Ray::new(Vector3::new(20.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let color = whitted.radiance(&scene, lit, sampler.as_mut());
+        assert!((color.r - 0.5 / std::f64::consts::PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_direct_lighting_depth_one() {
+        // a diffuse floor below an emissive ceiling reflects its albedo times the emitted radiance
+        let yaml = "
+width: 10
+height: 10
+background: [0, 0, 0]
+camera:
+  eye: [0, 0, 5]
+  look_at: [0, 0, 0]
+  up: [0, 1, 0]
+  fovy: 45
+objects:
+  - type: Plane
+    center: [0, 0, 0]
+    normal: [0, 1, 0]
+    material:
+      type: Lambertian
+      albedo: [0.5, 0.5, 0.5]
+  - type: Plane
+    center: [0, 1, 0]
+    normal: [0, -1, 0]
+    material:
+      type: Emissive
+      color: [1, 1, 1]
+";
+        let mut scene: Scene = serde_yaml::from_str(yaml).unwrap();
+        scene.precompute();
+        let mut sampler = SamplerType::Independent.build(0);
+        for integrator in [IntegratorType::Direct, IntegratorType::Path] {
+            let integrator = integrator.build(1, 64);
+            for _ in 0..50 {
+                let ray = Ray::new(Vector3::new(0.0, 0.5, 0.0), Vector3::new(0.3, -1.0, 0.2));
+                let color = integrator.radiance(&scene, ray, sampler.as_mut());
+                assert!((color.g - 0.5).abs() < 1e-9, "{:?}", color);
+            }
+        }
+    }
+
+    #[test]
+    fn test_russian_roulette() {
+        let integrator = PathIntegrator {
+            max_depth: 10,
+            rr_depth: 3,
+        };
+        let dim = Color::new(0.25, 0.1, 0.0);
+
+        // paths always survive below the roulette depth, however dim they are
+        for depth in 0..2 {
+            assert_eq!(integrator.russian_roulette(depth, dim, 0.99), Some(dim));
+        }
+        // afterwards they survive with the probability of their brightest channel and are scaled up by it
+        assert_eq!(
+            integrator.russian_roulette(2, dim, 0.2),
+            Some(Color::new(1.0, 0.4, 0.0))
+        );
+        assert_eq!(integrator.russian_roulette(2, dim, 0.25), None);
+        assert_eq!(integrator.russian_roulette(5, dim, 0.3), None);
+        let bright = Color::new(2.0, 1.0, 0.5);
+        assert_eq!(integrator.russian_roulette(2, bright, 0.99), Some(bright));
+        let black = Color::new(0.0, 0.0, 0.0);
+        assert_eq!(integrator.russian_roulette(2, black, 0.0), None);
+    }
+
+    /// Corridor between two parallel mirrors, closed by a glass pane and a light behind it
+    fn create_corridor_scene() -> Scene {
+        let yaml = "
+width: 10
+height: 10
+background: [0, 0, 0]
+camera:
+  eye: [0, 0, 5]
+  look_at: [0, 0, 0]
+  up: [0, 1, 0]
+  fovy: 45
+objects:
+  - type: Plane
+    center: [-1, 0, 0]
+    normal: [1, 0, 0]
+    material:
+      type: Metal
+      albedo: [0.9, 0.8, 0.7]
+      fuzziness: 0.0
+  - type: Plane
+    center: [1, 0, 0]
+    normal: [-1, 0, 0]
+    material:
+      type: Metal
+      albedo: [0.9, 0.8, 0.7]
+      fuzziness: 0.0
+  - type: Plane
+    center: [0, 0, -1]
+    normal: [0, 0, 1]
+    material:
+      type: Dieletrics
+      tint: [1, 1, 1]
+      refraction_index: 1.5
+  - type: Plane
+    center: [0, 0, -3]
+    normal: [0, 0, 1]
+    material:
+      type: Emissive
+      color: [1, 1, 1]
+";
+        let mut scene: Scene = serde_yaml::from_str(yaml).unwrap();
+        scene.precompute();
+        scene
+    }
+
+    fn mean_radiance(scene: &Scene, integrator: &dyn Integrator, samples: usize) -> Color {
+        let mut sampler = SamplerType::Independent.build(7);
+        let mut sum = Color::new(0.0, 0.0, 0.0);
+        for _ in 0..samples {
+            let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, -0.1));
+            sum += integrator.radiance(scene, ray, sampler.as_mut());
+        }
+        sum * (1.0 / samples as f64)
+    }
+
+    #[test]
+    fn test_russian_roulette_corridor() {
+        let scene = create_corridor_scene();
+        let samples = 4000;
+        // without Russian roulette every path bounces between the mirrors until it passes the pane or is reflected
+        let reference = mean_radiance(
+            &scene,
+            &PathIntegrator {
+                max_depth: 100,
+                rr_depth: 100,
+            },
+            samples,
+        );
+        let roulette = mean_radiance(
+            &scene,
+            &PathIntegrator {
+                max_depth: 100,
+                rr_depth: 1,
+            },
+            samples,
+        );
+        assert!(reference.max_channel() > 0.01);
+        for (expected, actual) in [
+            (reference.r, roulette.r),
+            (reference.g, roulette.g),
+            (reference.b, roulette.b),
+        ] {
+            assert!(
+                (expected - actual).abs() < 0.1 * expected + 0.005,
+                "{} != {}",
+                expected,
+                actual
+            );
+        }
+        // a depth cap below the number of bounces to the light cuts off all of its radiance
+        let capped = mean_radiance(
+            &scene,
+            &PathIntegrator {
+                max_depth: 5,
+                rr_depth: 5,
+            },
+            100,
+        );
+        assert!(capped.is_black());
+    }
+}
@@ -1,8 +1,36 @@
+mod adaptive;
 mod anti_aliasing;
+mod aov;
 mod camera;
+mod checkpoint;
+mod film;
 mod image;
+mod integrator;
+mod progressive;
 mod raytrace;
+mod sampler;
 mod scene;
+mod stats;
 
+pub use adaptive::AdaptiveSampling;
 pub use anti_aliasing::SuperSampling;
-pub use raytrace::compute_image;
+pub use aov::Aov;
+pub use camera::Camera;
+pub use film::Filter;
+pub use image::Color;
+pub use integrator::{Integrator, IntegratorType};
+pub use progressive::{parse_seconds, Progressive, WriteInterval};
+pub use raytrace::{
+    compute_image, render, render_with_camera, Framebuffer, OutputSettings, Ray, RenderSettings,
+};
+pub use sampler::{Sampler, SamplerType};
+pub use scene::lights::{DirectionalLight, EnvironmentConfig, Light, PointLight, SpotLight};
+pub use scene::materials::{
+    DielectricsMaterial, EmissiveMaterial, LambertianMaterial, Material, MetalMaterial,
+    TextureMaterial,
+};
+pub use scene::mesh::{load_obj, Mesh, Triangle};
+pub use scene::{
+    CameraConfig, Object, PhysicalCameraConfig, Plane, Projection, Scene, Sphere, StereoConfig,
+    StereoLayout,
+};
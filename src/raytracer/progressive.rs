@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+/// Progressive rendering in passes which add one sample to every pixel of the film.
+/// The image can be written after every few passes and the render stops once `target_spp` passes are done
+/// or the `time_limit` is exceeded, whichever happens first.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Progressive {
+    /// number of samples per pixel after which the render is finished
+    pub target_spp: Option<usize>,
+    /// render time after which no further pass is started
+    pub time_limit: Option<Duration>,
+    /// how often the intermediate image is written
+    pub write_interval: WriteInterval,
+}
+
+impl Progressive {
+    /// Returns `true` if no further pass should be rendered
+    ///
+    /// # Arguments
+    ///
+    /// * `passes` number of passes rendered so far
+    /// * `elapsed` time since the first pass was started
+    pub fn is_finished(&self, passes: usize, elapsed: Duration) -> bool {
+        self.target_spp.is_some_and(|target| passes >= target)
+            || self.time_limit.is_some_and(|limit| elapsed >= limit)
+    }
+}
+
+/// Interval in which the intermediate image of a progressive render is written
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WriteInterval {
+    /// Write after the given number of passes
+    Passes(usize),
+    /// Write after a pass once the given time passed since the last write
+    Time(Duration),
+}
+
+impl WriteInterval {
+    /// Returns `true` if the image should be written again
+    ///
+    /// # Arguments
+    ///
+    /// * `passes` number of passes rendered since the image was written last
+    /// * `elapsed` time since the image was written last
+    pub fn is_due(&self, passes: usize, elapsed: Duration) -> bool {
+        match self {
+            WriteInterval::Passes(interval) => passes >= *interval,
+            WriteInterval::Time(interval) => elapsed >= *interval,
+        }
+    }
+}
+
+/// Error Type for Decoding a Duration in Seconds from a String
+pub struct SecondsDecodeError {
+    error: String,
+}
+
+impl SecondsDecodeError {
+    fn new(error: String) -> SecondsDecodeError {
+        SecondsDecodeError { error }
+    }
+}
+
+impl std::error::Error for SecondsDecodeError {}
+
+impl std::fmt::Display for SecondsDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing seconds argument: {}", self.error)
+    }
+}
+
+impl std::fmt::Debug for SecondsDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing seconds argument: {}", self.error)
+    }
+}
+
+/// Parses a non-negative number of seconds (e.g. `2.5`) into a duration.
+/// Infinite numbers and numbers too large for a duration are rejected instead of panicking.
+pub fn parse_seconds(s: &str) -> Result<Duration, SecondsDecodeError> {
+    let seconds = s
+        .parse::<f64>()
+        .map_err(|_| SecondsDecodeError::new("seconds have to be a number".to_string()))?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| {
+        SecondsDecodeError::new(
+            "seconds have to be a non-negative number within the range of a duration".to_string(),
+        )
+    })
+}
+
+/// Error Type for Decoding a WriteInterval-Variant from a String
+pub struct WriteIntervalDecodeError {
+    error: String,
+}
+
+impl WriteIntervalDecodeError {
+    fn new(error: String) -> WriteIntervalDecodeError {
+        WriteIntervalDecodeError { error }
+    }
+}
+
+impl std::error::Error for WriteIntervalDecodeError {}
+
+impl std::fmt::Display for WriteIntervalDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing write interval argument: {}", self.error)
+    }
+}
+
+impl std::fmt::Debug for WriteIntervalDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing write interval argument: {}", self.error)
+    }
+}
+
+impl std::str::FromStr for WriteInterval {
+    type Err = WriteIntervalDecodeError;
+
+    /// Parses a number of passes (e.g. `16`) or a number of seconds with the suffix `s` (e.g. `30s`)
+    fn from_str(s: &str) -> Result<WriteInterval, WriteIntervalDecodeError> {
+        match s.strip_suffix('s') {
+            Some(seconds) => match parse_seconds(seconds) {
+                Ok(interval) if !interval.is_zero() => Ok(WriteInterval::Time(interval)),
+                _ => Err(WriteIntervalDecodeError::new(
+                    "seconds have to be a positive number".to_string(),
+                )),
+            },
+            None => match s.parse::<usize>() {
+                Ok(passes) if passes > 0 => Ok(WriteInterval::Passes(passes)),
+                _ => Err(WriteIntervalDecodeError::new(
+                    "passes have to be a positive integer".to_string(),
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use super::{parse_seconds, Progressive, WriteInterval};
+
+    #[test]
+    fn test_is_finished() {
+        let progressive = Progressive {
+            target_spp: Some(16),
+            time_limit: Some(Duration::from_secs(10)),
+            write_interval: WriteInterval::Passes(4),
+        };
+        assert!(!progressive.is_finished(15, Duration::from_secs(9)));
+        assert!(progressive.is_finished(16, Duration::from_secs(9)));
+        assert!(progressive.is_finished(15, Duration::from_secs(10)));
+
+        let time_only = Progressive {
+            target_spp: None,
+            ..progressive
+        };
+        assert!(!time_only.is_finished(1000, Duration::from_secs(9)));
+    }
+
+    #[test]
+    fn test_write_interval() {
+        assert!(WriteInterval::Passes(4).is_due(4, Duration::ZERO));
+        assert!(!WriteInterval::Passes(4).is_due(3, Duration::from_secs(100)));
+        assert!(WriteInterval::Time(Duration::from_secs(5)).is_due(1, Duration::from_secs(5)));
+        assert!(!WriteInterval::Time(Duration::from_secs(5)).is_due(100, Duration::from_secs(4)));
+    }
+
+    #[test]
+    fn test_write_interval_from_str() {
+        assert_eq!(
+            WriteInterval::from_str("16").unwrap(),
+            WriteInterval::Passes(16)
+        );
+        assert_eq!(
+            WriteInterval::from_str("2.5s").unwrap(),
+            WriteInterval::Time(Duration::from_millis(2500))
+        );
+        assert!(WriteInterval::from_str("0").is_err());
+        assert!(WriteInterval::from_str("-1s").is_err());
+        assert!(WriteInterval::from_str("often").is_err());
+        assert!(WriteInterval::from_str("infs").is_err());
+        assert!(WriteInterval::from_str("1e30s").is_err());
+    }
+
+    #[test]
+    fn test_parse_seconds() {
+        assert_eq!(parse_seconds("2.5").unwrap(), Duration::from_millis(2500));
+        assert_eq!(parse_seconds("0").unwrap(), Duration::ZERO);
+        for invalid in ["-1", "inf", "NaN", "1e30", "ten"] {
+            assert!(parse_seconds(invalid).is_err());
+        }
+    }
+}
@@ -5,34 +5,55 @@ use crate::raytracer::image;
 use crate::raytracer::image::Color;
 use crate::raytracer::scene;
 
-use rand::Rng;
 use rayon::prelude::*;
-use serde_yaml;
 use std::fs;
+use std::io;
 use std::path;
+use std::time::{Duration, Instant};
 
-use super::scene::materials::Material;
-use super::scene::materials::Scatter;
+use super::adaptive::{self, AdaptiveSampling, PixelStatistics};
+use super::aov::Aov;
+use super::checkpoint::{self, CameraCheckpoint, Checkpoint};
+use super::film::{Film, Filter};
+use super::integrator::Integrator;
+use super::progressive::Progressive;
+use super::sampler::SamplerType;
+use super::stats::{self, ProgressReporter, RayStats, RenderProgress, RenderTimings};
 
 /// Basic structure representing a ray being cast into the scene.
 /// A ray consists of an origin point `o` and a direction `d`. It's position can therefore
-/// be calculated for any timestep `t` by `o + t * d`
+/// be calculated for any timestep `t` by `o + t * d`.
+/// Additionally each ray carries the point in `time` at which it travels through the scene, which is used to
+/// evaluate moving objects for motion blur.
 pub struct Ray {
     pub origin: Vector3,
     pub direction: Vector3,
+    pub time: f64,
 }
 
 impl Ray {
-    /// Creates a new ray with the given origin and direction vector
+    /// Creates a new ray with the given origin and direction vector at the start of the frame (`time` is `0.0`)
     ///
     /// # Arguments
     ///
     /// * `origin` Origin of the Ray (for primary rays this is the camera position / eye)
     /// * `direction` Direction of the ray to determine it's movement in space
     pub fn new(origin: Vector3, direction: Vector3) -> Ray {
+        Ray::with_time(origin, direction, 0.0)
+    }
+
+    /// Creates a new ray with the given origin and direction vector at a point in time
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` Origin of the Ray
+    /// * `direction` Direction of the ray to determine it's movement in space
+    /// * `time` point in time in the frame, where objects are at their start position at `0.0` and at their end position at `1.0`
+    pub fn with_time(origin: Vector3, direction: Vector3, time: f64) -> Ray {
         Ray {
             origin,
             direction: direction.normalized(),
+            time,
         }
     }
 
@@ -40,117 +61,1251 @@ impl Ray {
     pub fn at_timestep(&self, t: f64) -> Vector3 {
         self.origin + self.direction * t
     }
+}
 
-    /// Traces the ray through the scene to calculate the resulting pixel color
-    ///
-    /// # Arguments
-    ///
-    /// * `scene` The current scene
-    /// * `depth` if the material of the object is mirroring, depth defines the recursion depth for which to spawn
-    ///           secondary rays
-    fn trace(&self, scene: &scene::Scene, current_depth: u8, max_depth: u8) -> Color {
-        if current_depth == max_depth {
-            return Color::new(0.0, 0.0, 0.0);
-        }
-
-        let intersection = scene.get_closest_interesection(self);
-        if let Some(intersection_info) = &intersection {
-            let scattered = intersection_info.material.scatter(self, intersection_info);
-            return match scattered {
-                Some((scattered_ray, albedo)) => match scattered_ray {
-                    Some(scatter) => {
-                        let scattered_color = scatter.trace(scene, current_depth + 1, max_depth);
-
-                        let mut prob = 0.1;
-
-                        if let Material::Dieletrics(_) = intersection_info.material {
-                            prob = 0.05;
-                        }
-
-                        let mut rng = rand::thread_rng();
-
-                        let mut light_color = Color::new(0.0, 0.0, 0.0);
-                        let lights_len = scene.lights.len() as f64;
-                        if lights_len > 0.0
-                            && rng.gen::<f64>() > (1.0 - lights_len * prob)
-                            && current_depth == (max_depth - 1)
-                        {
-                            for l in &scene.lights {
-                                let shadow_ray = Ray::new(
-                                    intersection_info.point,
-                                    l.sample_points[0] - intersection_info.point,
-                                );
-                                let target_color = shadow_ray.trace(scene, 0, 1);
-                                light_color += albedo * target_color
-                            }
-                            light_color /= lights_len;
-                        }
-                        light_color + (albedo * scattered_color)
-                    }
-                    None => albedo,
-                },
-                None => Color::new(0.0, 0.0, 0.0),
-            };
+/// Settings determining how the samples of each pixel are taken and combined.
+/// The default takes 4 jittered samples per pixel with the independent sampler, combines them with a box filter
+/// and renders on one thread per logical core without reporting the progress.
+pub struct RenderSettings {
+    /// Algorithm to use for super sampling anti aliasing
+    pub ssaa: anti_aliasing::SuperSampling,
+    /// reconstruction filter weighting the samples of the surrounding pixels
+    pub filter: Filter,
+    /// sampler providing the random numbers of each sample
+    pub sampler: SamplerType,
+    /// seed of all random numbers, rendering the same scene with the same settings and seed results in the same image
+    pub seed: u64,
+    /// adaptive sampling replacing the fixed number of samples of `ssaa`
+    pub adaptive: Option<AdaptiveSampling>,
+    /// progressive rendering in passes of one sample per pixel replacing the fixed number of samples of `ssaa`
+    pub progressive: Option<Progressive>,
+    /// number of worker threads rendering tiles, `0` uses one thread per logical core
+    pub threads: usize,
+    /// `true` to print the progress while rendering and statistics of the finished render to stderr
+    pub report_progress: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings {
+            ssaa: anti_aliasing::SuperSampling::Jitter(2),
+            filter: Filter::Box(0.5),
+            sampler: SamplerType::Independent,
+            seed: 0,
+            adaptive: None,
+            progressive: None,
+            threads: 0,
+            report_progress: false,
         }
-        scene.background
     }
 }
 
+/// Settings of the files written next to the output image by [compute_image] besides the image itself.
+/// By default neither a heatmap nor checkpoints are written.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OutputSettings {
+    /// `true` to write a heatmap of the number of samples of each pixel next to the output image
+    pub sample_heatmap: bool,
+    /// time after which the accumulated film is written to a checkpoint next to the output image again
+    pub checkpoint_interval: Option<Duration>,
+    /// `true` to continue the render from the checkpoint next to the output image if there is one
+    pub resume: bool,
+}
+
+/// Width and height of the tiles an image is split into, smaller tiles at the borders are cut off by the image
+const TILE_SIZE: usize = 16;
+
+/// Number of tiles per worker thread rendered at once, between these chunks checkpoints can be written
+const TILES_PER_THREAD: usize = 4;
+
+/// Renders the image of a scene in memory.
+/// Stereo cameras render a view for each eye, which are joined into a single framebuffer according to their layout.
+/// Progress is printed to stderr if `report_progress` is set, the image is never written to a file.
+///
+/// # Arguments
+///
+/// * `scene` The scene to render, which has to be [precomputed](scene::Scene::precompute)
+/// * `settings` sampling and reconstruction of the pixels
+/// * `integrator` light transport algorithm used to compute the color of each sample
+///
+/// # Panics
+///
+/// If the scene hasn't been precomputed.
+pub fn render(
+    scene: &scene::Scene,
+    settings: &RenderSettings,
+    integrator: &dyn Integrator,
+) -> Framebuffer {
+    let images = render_cameras(scene, &scene_cameras(scene), settings, integrator);
+    join_images(scene, images)
+}
+
+/// Renders the image of a scene in memory as seen by the given camera instead of the one configured in the scene,
+/// e.g. to render several views of the same scene. The image has the size of the scene.
+///
+/// # Arguments
+///
+/// * `scene` The scene to render, which has to be [precomputed](scene::Scene::precompute)
+/// * `camera` camera spawning the primary rays, constructed for the size of the scene
+/// * `settings` sampling and reconstruction of the pixels
+/// * `integrator` light transport algorithm used to compute the color of each sample
+///
+/// # Panics
+///
+/// If the scene hasn't been precomputed.
+pub fn render_with_camera(
+    scene: &scene::Scene,
+    camera: &camera::Camera,
+    settings: &RenderSettings,
+    integrator: &dyn Integrator,
+) -> Framebuffer {
+    let image = render_cameras(scene, std::slice::from_ref(camera), settings, integrator)
+        .pop()
+        .unwrap();
+    Framebuffer {
+        width: scene.width,
+        height: scene.height,
+        pixel_colors: image.pixel_colors,
+        sample_counts: image.sample_counts,
+    }
+}
+
+/// Renders the images of the cameras on a thread pool configured by the `settings` (see [render])
+fn render_cameras(
+    scene: &scene::Scene,
+    cameras: &[camera::Camera],
+    settings: &RenderSettings,
+    integrator: &dyn Integrator,
+) -> Vec<RenderedImage> {
+    assert!(
+        scene.is_precomputed(),
+        "the scene has to be precomputed before rendering"
+    );
+    thread_pool(settings).install(|| {
+        let mut renderer = Renderer::new(scene, cameras, settings, integrator);
+        run_renderer(
+            &mut renderer,
+            settings,
+            RenderTimings::default(),
+            &mut |_, _| {},
+        );
+        renderer.images()
+    })
+}
+
 /// Computes the image for a given scene config (loaded from `scene_path`) by raytracing and saves it to the specified `output_path`.
 /// For more details on scene configs see [Scene](crate::raytracer::scene::Scene).
+/// With a `checkpoint_interval` the accumulated film is periodically written to a checkpoint next to the output image
+/// (e.g. `out.checkpoint` for `out.png`), which a render with `resume` continues from as long as the scene file,
+/// its assets and the settings determining the samples are unchanged.
+/// The checkpoint is removed once all samples are taken, but kept for a progressive render stopped by its time limit,
+/// which can be continued with a higher time limit or target.
 ///
 /// # Arguments
 ///
-/// * `ssaa` Algorithm to use for super sampling anti aliasing
-/// * `depth` determines the maximum ray bounce / tracing recursion depth
+/// * `settings` sampling and reconstruction of the pixels
+/// * `output` heatmap and checkpoints written next to the output image
+/// * `integrator` light transport algorithm used to compute the color of each sample
+/// * `aovs` debug outputs written next to the output image (see [Aov::output_path])
+/// * `beauty` `false` to skip rendering the image itself and only write the `aovs`
 /// * `scene_path` Path to the scene file determining the needed properties for raytracing
 /// * `output_path` Path of the output image file
 pub fn compute_image(
-    ssaa: anti_aliasing::SuperSampling,
-    depth: u8,
+    settings: &RenderSettings,
+    output: &OutputSettings,
+    integrator: &dyn Integrator,
+    aovs: &[Aov],
+    beauty: bool,
     scene_path: &path::Path,
     output_path: &path::Path,
 ) {
-    let scene_file = fs::File::open(scene_path).unwrap();
-    let mut scene: scene::Scene = serde_yaml::from_reader(scene_file).unwrap();
-
-    let camera = camera::Camera::new(
-        scene.camera.eye,
-        scene.camera.look_at,
-        scene.camera.up,
-        scene.camera.fovy,
-        scene.width,
-        scene.height,
-    );
+    thread_pool(settings).install(|| {
+        render_scene(
+            settings,
+            output,
+            integrator,
+            aovs,
+            beauty,
+            scene_path,
+            output_path,
+        )
+    });
+}
+
+/// Returns a thread pool with the number of worker threads of the settings
+fn thread_pool(settings: &RenderSettings) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(settings.threads)
+        .build()
+        .unwrap()
+}
+
+/// Returns the cameras to render an image for, the left eye is the first camera for stereo cameras
+fn scene_cameras(scene: &scene::Scene) -> Vec<camera::Camera> {
+    match &scene.camera.stereo {
+        Some(stereo) => {
+            let (left, right) =
+                camera::Camera::new_stereo(&scene.camera, stereo, scene.width, scene.height);
+            vec![left, right]
+        }
+        None => vec![camera::Camera::new(
+            &scene.camera,
+            scene.width,
+            scene.height,
+        )],
+    }
+}
+
+/// Loads the scene and renders the AOVs and image on the current thread pool (see [compute_image])
+fn render_scene(
+    settings: &RenderSettings,
+    output: &OutputSettings,
+    integrator: &dyn Integrator,
+    aovs: &[Aov],
+    beauty: bool,
+    scene_path: &path::Path,
+    output_path: &path::Path,
+) {
+    let mut timings = RenderTimings::default();
+    let start = Instant::now();
+    let scene_source = fs::read(scene_path).unwrap();
+    let mut scene: scene::Scene = serde_yaml::from_slice(&scene_source).unwrap();
+    timings.scene_load = Some(start.elapsed());
+
+    let camera = camera::Camera::new(&scene.camera, scene.width, scene.height);
+    let start = Instant::now();
     scene.precompute();
-    let pixel_colors: Vec<Vec<Color>> = (0..scene.height)
-        .into_par_iter()
-        .rev()
-        .map(|j: usize| {
-            (0..scene.width)
-                .into_par_iter()
-                .map(|i: usize| {
-                    let samples = ssaa.sample(i, j);
-                    let mut pixel_color = image::Color::new(0.0, 0.0, 0.0);
-                    let count = samples.len();
-                    let samples_color = samples
-                        .into_par_iter()
-                        .map(|sample| {
-                            let ray = camera.spawn_ray(sample.0, sample.1);
-                            ray.trace(&scene, 0, depth)
-                        })
-                        .reduce(|| Color::new(0.0, 0.0, 0.0), |a, b| a + b);
-                    pixel_color += samples_color;
-                    pixel_color /= count as f64;
-                    // Gamma adjustment
-                    pixel_color.r = pixel_color.r.sqrt();
-                    pixel_color.g = pixel_color.g.sqrt();
-                    pixel_color.b = pixel_color.b.sqrt();
-                    pixel_color.clamp();
-                    pixel_color
+    timings.precompute = Some(start.elapsed());
+    for aov in aovs {
+        let pixel_colors = aov.render(&scene, &camera);
+        image::write_image(
+            &pixel_colors,
+            scene.width,
+            scene.height,
+            &aov.output_path(output_path),
+        );
+    }
+    if !beauty {
+        return;
+    }
+
+    let cameras = scene_cameras(&scene);
+    let checkpoint_path = Checkpoint::path(output_path);
+    // hashing reads all assets again, so it's skipped for renders without checkpoints
+    let scene_hash = if output.resume || output.checkpoint_interval.is_some() {
+        match checkpoint::scene_hash(&scene_source) {
+            Ok(scene_hash) => Some(scene_hash),
+            Err(error) => {
+                eprintln!(
+                    "can't hash the scene assets for checkpoints, rendering without them: {}",
+                    error
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let mut renderer = Renderer::new(&scene, &cameras, settings, integrator);
+    if let (true, Some(scene_hash)) = (output.resume, scene_hash) {
+        match Checkpoint::read(&checkpoint_path) {
+            Ok(checkpoint) => {
+                if let Err(error) = renderer.restore(checkpoint, scene_hash) {
+                    panic!("can't resume from {}: {}", checkpoint_path.display(), error);
+                }
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => eprintln!(
+                "no checkpoint found at {}, starting a new render",
+                checkpoint_path.display()
+            ),
+            Err(error) => panic!(
+                "can't read checkpoint {}: {}",
+                checkpoint_path.display(),
+                error
+            ),
+        }
+    }
+
+    let checkpoint_interval = scene_hash.and(output.checkpoint_interval);
+    let mut last_checkpoint = Instant::now();
+    let complete = run_renderer(
+        &mut renderer,
+        settings,
+        timings,
+        &mut |renderer, event| match event {
+            RenderEvent::TilesRendered => {
+                if let (Some(scene_hash), Some(interval)) = (scene_hash, checkpoint_interval) {
+                    if last_checkpoint.elapsed() >= interval {
+                        // a failed checkpoint shouldn't end the render it protects, it's retried at the next interval
+                        write_checkpoint(renderer, scene_hash, &checkpoint_path);
+                        last_checkpoint = Instant::now();
+                    }
+                }
+            }
+            RenderEvent::ProgressDue => write_output(
+                settings,
+                output,
+                &join_images(&scene, renderer.images()),
+                output_path,
+            ),
+        },
+    );
+    write_output(
+        settings,
+        output,
+        &join_images(&scene, renderer.images()),
+        output_path,
+    );
+    let scene_hash = match scene_hash {
+        Some(scene_hash) => scene_hash,
+        None => return,
+    };
+    // the checkpoint of a finished render is of no use anymore, while a render stopped by its time limit
+    // can be continued with a higher time limit or target
+    if !complete && checkpoint_interval.is_some() {
+        write_checkpoint(&renderer, scene_hash, &checkpoint_path);
+    } else if complete && checkpoint_path.exists() {
+        if let Err(error) = fs::remove_file(&checkpoint_path) {
+            eprintln!(
+                "can't remove checkpoint {}: {}",
+                checkpoint_path.display(),
+                error
+            );
+        }
+    }
+}
+
+/// Writes the state of the renderer to a checkpoint and prints a warning if it can't be written
+///
+/// # Arguments
+///
+/// * `renderer` renderer whose state is saved
+/// * `scene_hash` hash of the scene file and its assets (see [checkpoint::scene_hash])
+/// * `path` path of the checkpoint
+fn write_checkpoint(renderer: &Renderer, scene_hash: u64, path: &path::Path) {
+    if let Err(error) = renderer.checkpoint(scene_hash).write(path) {
+        eprintln!("can't write checkpoint {}: {}", path.display(), error);
+    }
+}
+
+/// Runs the renderer and reports its progress and statistics if requested by the settings
+///
+/// # Arguments
+///
+/// * `renderer` renderer to run until the image is finished
+/// * `settings` sampling and reconstruction of the pixels
+/// * `timings` durations of the stages before the render, the duration of the render is added to them
+/// * `observer` called with the events of the render (see [Renderer::run])
+///
+/// Returns `true` if all samples of the image were taken (see [Renderer::run]).
+fn run_renderer(
+    renderer: &mut Renderer,
+    settings: &RenderSettings,
+    mut timings: RenderTimings,
+    observer: &mut dyn FnMut(&Renderer, RenderEvent),
+) -> bool {
+    let start = Instant::now();
+    let progressive = settings.progressive.as_ref();
+    let mut reporter =
+        ProgressReporter::new(renderer.progress().fraction(progressive, Duration::ZERO));
+    let complete = renderer.run(&mut |renderer, event| {
+        if settings.report_progress && event == RenderEvent::TilesRendered {
+            reporter.report(renderer.progress(), progressive);
+        }
+        observer(renderer, event);
+    });
+    timings.render = start.elapsed();
+    if settings.report_progress {
+        reporter.finish(renderer.progress(), progressive);
+        stats::print_summary(&renderer.ray_stats(), &timings);
+    }
+    complete
+}
+
+/// Joins the images of all cameras into a single framebuffer, where the eyes of a stereo camera are placed
+/// according to the layout of the camera
+///
+/// # Arguments
+///
+/// * `scene` The current scene
+/// * `images` rendered images of the cameras, the left eye is the first image for stereo cameras
+fn join_images(scene: &scene::Scene, images: Vec<RenderedImage>) -> Framebuffer {
+    let mut images = images.into_iter();
+    let first = images.next().unwrap();
+    match (&scene.camera.stereo, images.next()) {
+        (Some(stereo), Some(right)) => match stereo.layout {
+            scene::StereoLayout::SideBySide => Framebuffer {
+                width: 2 * scene.width,
+                height: scene.height,
+                pixel_colors: image::join_side_by_side(
+                    &first.pixel_colors,
+                    &right.pixel_colors,
+                    scene.width,
+                ),
+                sample_counts: image::join_side_by_side(
+                    &first.sample_counts,
+                    &right.sample_counts,
+                    scene.width,
+                ),
+            },
+            scene::StereoLayout::OverUnder => Framebuffer {
+                width: scene.width,
+                height: 2 * scene.height,
+                pixel_colors: image::join_over_under(first.pixel_colors, right.pixel_colors),
+                sample_counts: image::join_over_under(first.sample_counts, right.sample_counts),
+            },
+        },
+        _ => Framebuffer {
+            width: scene.width,
+            height: scene.height,
+            pixel_colors: first.pixel_colors,
+            sample_counts: first.sample_counts,
+        },
+    }
+}
+
+/// Writes the framebuffer to `output_path`. If requested the heatmap of the samples per pixel is written next to it.
+///
+/// # Arguments
+///
+/// * `settings` sampling and reconstruction of the pixels
+/// * `output` files written next to the output image
+/// * `framebuffer` the rendered image
+/// * `output_path` Path of the output image file
+fn write_output(
+    settings: &RenderSettings,
+    output: &OutputSettings,
+    framebuffer: &Framebuffer,
+    output_path: &path::Path,
+) {
+    if output.sample_heatmap {
+        let max_samples = match &settings.adaptive {
+            Some(adaptive) => adaptive.max_samples,
+            None => framebuffer.sample_counts.iter().copied().max().unwrap_or(1),
+        };
+        image::write_image(
+            &adaptive::sample_heatmap(&framebuffer.sample_counts, max_samples),
+            framebuffer.width,
+            framebuffer.height,
+            &image::suffixed_path(output_path, "samples"),
+        );
+    }
+    framebuffer.write_png(output_path);
+}
+
+/// Rendered image held in memory with its pixels in row -> column order, starting with the top row
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    /// gamma corrected colors of the pixels with channels between `0.0` and `1.0`
+    pub pixel_colors: Vec<Color>,
+    /// number of samples taken for each pixel
+    pub sample_counts: Vec<usize>,
+}
+
+impl Framebuffer {
+    /// Returns the color of the pixel in column `x` and row `y`, where row `0` is the top row
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixel_colors[y * self.width + x]
+    }
+
+    /// Returns the pixels as 8 bit RGB triples in row -> column order, starting with the top row
+    pub fn to_rgb8(&self) -> Vec<u8> {
+        self.pixel_colors
+            .iter()
+            .flat_map(|color| color.to_output())
+            .collect()
+    }
+
+    /// Writes the image to a png file at the given `output_path`
+    ///
+    /// # Panics
+    ///
+    /// If the file cannot be written to.
+    pub fn write_png(&self, output_path: &path::Path) {
+        image::write_image(&self.pixel_colors, self.width, self.height, output_path);
+    }
+}
+
+/// Pixels of the image of a single camera in row -> column order, starting with the top row
+struct RenderedImage {
+    /// gamma corrected colors of the pixels
+    pixel_colors: Vec<Color>,
+    /// number of samples taken for each pixel
+    sample_counts: Vec<usize>,
+}
+
+/// Events during a render at which the state of the [Renderer] can be inspected
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum RenderEvent {
+    /// A chunk of tiles was rendered and splatted into the film
+    TilesRendered,
+    /// A pass of a progressive render finished and the intermediate images should be written
+    ProgressDue,
+}
+
+/// Renders the images seen by a set of cameras in passes over their tiles.
+/// Without progressive rendering a single pass takes all samples of each pixel. Progressive renders add a sample to
+/// every pixel of every camera in each pass until a stop condition is reached.
+/// Each pass renders the tiles in chunks, so the state of the render can be saved to a [Checkpoint] in between and
+/// restored by a later render.
+struct Renderer<'a> {
+    settings: &'a RenderSettings,
+    /// hash of the settings and integrator (see [checkpoint::settings_hash])
+    settings_hash: u64,
+    renders: Vec<CameraRender<'a>>,
+    /// number of finished passes
+    passes: usize,
+    /// number of tiles rendered at once
+    chunk_size: usize,
+}
+
+impl<'a> Renderer<'a> {
+    /// Creates a renderer without any samples taken
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` The current (precomputed) scene
+    /// * `cameras` cameras to render an image for
+    /// * `settings` sampling and reconstruction of the pixels
+    /// * `integrator` light transport algorithm used to compute the color of each sample
+    fn new(
+        scene: &'a scene::Scene,
+        cameras: &'a [camera::Camera],
+        settings: &'a RenderSettings,
+        integrator: &'a dyn Integrator,
+    ) -> Renderer<'a> {
+        let renders = cameras
+            .iter()
+            .map(|camera| {
+                CameraRender::new(RenderContext {
+                    scene,
+                    camera,
+                    settings,
+                    integrator,
                 })
-                .collect()
-        })
-        .collect();
-    image::write_image(pixel_colors, scene.width, scene.height, output_path);
+            })
+            .collect();
+        Renderer {
+            settings,
+            settings_hash: checkpoint::settings_hash(settings, integrator),
+            renders,
+            passes: 0,
+            chunk_size: rayon::current_num_threads() * TILES_PER_THREAD,
+        }
+    }
+
+    /// Renders passes until the image is finished. The `observer` is called after every chunk of tiles
+    /// and whenever the intermediate images of a progressive render should be written.
+    /// Returns `true` if all samples of the image were taken, which isn't the case for a progressive render
+    /// stopped by its time limit before it reached its target number of passes (or converged).
+    fn run(&mut self, observer: &mut dyn FnMut(&Renderer, RenderEvent)) -> bool {
+        let progressive = match self.settings.progressive {
+            Some(progressive) => progressive,
+            None => {
+                if self.passes == 0 {
+                    self.render_pass(observer);
+                }
+                return true;
+            }
+        };
+        let start = Instant::now();
+        let mut last_write = (self.passes, start);
+        loop {
+            // a resumed pass only covers the remaining tiles, so it can't tell whether all pixels converged
+            let complete_pass = self.renders.iter().all(|render| render.next_tile == 0);
+            let samples = self.render_pass(observer);
+            // without any sample taken all pixels converged by adaptive sampling
+            if complete_pass && samples == 0 {
+                return true;
+            }
+            if progressive.is_finished(self.passes, start.elapsed()) {
+                return progressive
+                    .target_spp
+                    .is_some_and(|target| self.passes >= target);
+            }
+            if progressive
+                .write_interval
+                .is_due(self.passes - last_write.0, last_write.1.elapsed())
+            {
+                observer(self, RenderEvent::ProgressDue);
+                last_write = (self.passes, Instant::now());
+            }
+        }
+    }
+
+    /// Renders the remaining tiles of the current pass of all cameras and returns the number of samples taken
+    fn render_pass(&mut self, observer: &mut dyn FnMut(&Renderer, RenderEvent)) -> usize {
+        let progressive = self.settings.progressive.is_some();
+        let mut samples = 0;
+        for idx in 0..self.renders.len() {
+            while self.renders[idx].next_tile < self.renders[idx].tiles.len() {
+                samples += self.renders[idx].render_tiles(self.chunk_size, progressive);
+                observer(self, RenderEvent::TilesRendered);
+            }
+        }
+        for render in self.renders.iter_mut() {
+            render.next_tile = 0;
+        }
+        self.passes += 1;
+        samples
+    }
+
+    /// Returns the current images of all cameras
+    fn images(&self) -> Vec<RenderedImage> {
+        self.renders.iter().map(CameraRender::image).collect()
+    }
+
+    /// Returns the position of the render in its passes over the tiles of all cameras
+    fn progress(&self) -> RenderProgress {
+        RenderProgress {
+            passes: self.passes,
+            tiles_done: self.renders.iter().map(|render| render.next_tile).sum(),
+            tiles: self.renders.iter().map(|render| render.tiles.len()).sum(),
+        }
+    }
+
+    /// Returns the rays traced by this renderer, not including the ones of a restored checkpoint
+    fn ray_stats(&self) -> RayStats {
+        let mut ray_stats = RayStats::default();
+        for render in &self.renders {
+            ray_stats += render.ray_stats;
+        }
+        ray_stats
+    }
+
+    /// Returns the accumulated state of the render
+    ///
+    /// # Arguments
+    ///
+    /// * `scene_hash` hash of the scene file and its assets (see [checkpoint::scene_hash])
+    fn checkpoint(&self, scene_hash: u64) -> Checkpoint {
+        Checkpoint {
+            scene_hash,
+            settings_hash: self.settings_hash,
+            seed: self.settings.seed,
+            passes: self.passes,
+            cameras: self.renders.iter().map(CameraRender::checkpoint).collect(),
+        }
+    }
+
+    /// Continues the render from a checkpoint, which has to be taken of the same scene with the same settings,
+    /// integrator and seed.
+    /// Returns the reason if the checkpoint doesn't belong to this render.
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint` accumulated state of an earlier render
+    /// * `scene_hash` hash of the scene file and its assets (see [checkpoint::scene_hash])
+    fn restore(&mut self, checkpoint: Checkpoint, scene_hash: u64) -> Result<(), String> {
+        if checkpoint.scene_hash != scene_hash {
+            return Err(
+                "the scene or its assets changed since the checkpoint was written".to_string(),
+            );
+        }
+        if checkpoint.settings_hash != self.settings_hash {
+            return Err(
+                "the checkpoint was rendered with other sampling, filter or integrator settings"
+                    .to_string(),
+            );
+        }
+        if checkpoint.seed != self.settings.seed {
+            return Err(format!(
+                "the checkpoint was rendered with seed {}",
+                checkpoint.seed
+            ));
+        }
+        if checkpoint.cameras.len() != self.renders.len() {
+            return Err("the checkpoint has a different number of cameras".to_string());
+        }
+        for (render, camera) in self.renders.iter_mut().zip(checkpoint.cameras) {
+            render.restore(camera)?;
+        }
+        self.passes = checkpoint.passes;
+        Ok(())
+    }
+}
+
+/// Everything needed to render the samples of one camera
+#[derive(Clone, Copy)]
+struct RenderContext<'a> {
+    scene: &'a scene::Scene,
+    camera: &'a camera::Camera,
+    settings: &'a RenderSettings,
+    integrator: &'a dyn Integrator,
+}
+
+impl RenderContext<'_> {
+    /// Takes samples of a single pixel and returns their positions on the image and radiance.
+    /// A progressive pass takes a single sample unless the pixel converged, otherwise samples are taken until
+    /// the pixel converged with adaptive sampling or on the super sampling grid.
+    ///
+    /// # Arguments
+    ///
+    /// * `i` coordinate of the pixel on the x-axis
+    /// * `j` coordinate of the pixel on the y-axis
+    /// * `statistics` statistics of the samples of the pixel taken so far, which the new samples are added to
+    /// * `progressive` `true` to only take the next sample of a progressive pass
+    fn render_pixel(
+        &self,
+        i: usize,
+        j: usize,
+        statistics: &mut PixelStatistics,
+        progressive: bool,
+    ) -> Vec<(f64, f64, Color)> {
+        let settings = self.settings;
+        let mut sampler = settings.sampler.build(settings.seed);
+        let mut trace = |index: usize, position: (f64, f64)| {
+            sampler.start_pixel_sample(i, j, index);
+            let (x, y) = sampler.get_pixel_2d(position);
+            let u_lens = sampler.get_2d();
+            let u_time = sampler.get_1d();
+            let color = match self.camera.spawn_ray(x, y, u_lens, u_time) {
+                Some(ray) => {
+                    stats::count_primary_ray();
+                    self.integrator.radiance(self.scene, ray, sampler.as_mut())
+                }
+                None => Color::new(0.0, 0.0, 0.0),
+            };
+            (x, y, color)
+        };
+
+        let samples = match (progressive, &settings.adaptive) {
+            (true, adaptive) => {
+                if adaptive.is_some_and(|adaptive| adaptive.is_converged(statistics)) {
+                    return Vec::new();
+                }
+                let index = statistics.count();
+                vec![trace(
+                    index,
+                    anti_aliasing::random_sample(i, j, index, settings.seed),
+                )]
+            }
+            (false, Some(adaptive)) => {
+                let mut samples = Vec::with_capacity(adaptive.min_samples);
+                while !adaptive.is_converged(statistics) {
+                    let index = statistics.count();
+                    let sample = trace(
+                        index,
+                        anti_aliasing::random_sample(i, j, index, settings.seed),
+                    );
+                    statistics.add(sample.2);
+                    samples.push(sample);
+                }
+                return samples;
+            }
+            (false, None) => settings
+                .ssaa
+                .sample(i, j, settings.seed)
+                .into_iter()
+                .enumerate()
+                .map(|(index, position)| trace(index, position))
+                .collect::<Vec<_>>(),
+        };
+        for sample in &samples {
+            statistics.add(sample.2);
+        }
+        samples
+    }
+}
+
+/// Rectangular block of pixels which is rendered as a whole by one worker thread
+struct Tile {
+    /// coordinate of the lower left pixel on the x-axis
+    x: usize,
+    /// coordinate of the lower left pixel on the y-axis
+    y: usize,
+    width: usize,
+    /// statistics of the samples of each pixel in row -> column order, starting with the bottom row
+    statistics: Vec<PixelStatistics>,
+}
+
+impl Tile {
+    /// Splits an image into tiles of [TILE_SIZE] in row -> column order, starting with the bottom row
+    fn split(width: usize, height: usize) -> Vec<Tile> {
+        let mut tiles = Vec::new();
+        for y in (0..height).step_by(TILE_SIZE) {
+            for x in (0..width).step_by(TILE_SIZE) {
+                let tile_width = TILE_SIZE.min(width - x);
+                let tile_height = TILE_SIZE.min(height - y);
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width: tile_width,
+                    statistics: vec![PixelStatistics::default(); tile_width * tile_height],
+                });
+            }
+        }
+        tiles
+    }
+
+    /// Returns the height of the tile
+    fn height(&self) -> usize {
+        self.statistics.len() / self.width
+    }
+
+    /// Renders the samples of all pixels of the tile, splats them into the film of the tile and returns their number
+    ///
+    /// # Arguments
+    ///
+    /// * `context` camera and settings to render the samples with
+    /// * `progressive` `true` to take a single sample per pixel as a pass of a progressive render
+    /// * `film` film covering the tile (see [Film::for_tile])
+    fn render(&mut self, context: &RenderContext, progressive: bool, film: &mut Film) -> usize {
+        let mut count = 0;
+        for (idx, statistics) in self.statistics.iter_mut().enumerate() {
+            let i = self.x + idx % self.width;
+            let j = self.y + idx / self.width;
+            for (x, y, color) in context.render_pixel(i, j, statistics, progressive) {
+                film.add_sample(x, y, color);
+                count += 1;
+            }
+        }
+        count
+    }
+}
+
+/// Film and tiles of the image of one camera, which are refined by every pass
+struct CameraRender<'a> {
+    context: RenderContext<'a>,
+    film: Film,
+    tiles: Vec<Tile>,
+    /// index of the next tile to render in the current pass
+    next_tile: usize,
+    /// rays traced for the image so far
+    ray_stats: RayStats,
+}
+
+impl<'a> CameraRender<'a> {
+    fn new(context: RenderContext<'a>) -> CameraRender<'a> {
+        let scene = context.scene;
+        CameraRender {
+            context,
+            film: Film::new(scene.width, scene.height, context.settings.filter),
+            tiles: Tile::split(scene.width, scene.height),
+            next_tile: 0,
+            ray_stats: RayStats::default(),
+        }
+    }
+
+    /// Renders the samples of the next `count` tiles of the pass and splats them into the film.
+    /// Returns the number of samples taken.
+    /// The tiles are distributed among the worker threads, which splat the samples of each tile into a film of its own.
+    /// These films are merged in the order of the tiles afterwards, so the floating point sums and therefore the image
+    /// don't depend on the scheduling of the threads or the number of tiles rendered at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` maximum number of tiles to render
+    /// * `progressive` `true` to take a single sample per pixel as a pass of a progressive render
+    fn render_tiles(&mut self, count: usize, progressive: bool) -> usize {
+        let context = self.context;
+        let end = (self.next_tile + count).min(self.tiles.len());
+        let film = &self.film;
+        let tile_films = self.tiles[self.next_tile..end]
+            .par_iter_mut()
+            .map(|tile| {
+                // a tile is rendered on a single thread, so the counts of the thread belong to the tile
+                stats::take_thread_stats();
+                let mut tile_film = film.for_tile(tile.x, tile.y, tile.width, tile.height());
+                let samples = tile.render(&context, progressive, &mut tile_film);
+                (tile_film, samples, stats::take_thread_stats())
+            })
+            .collect::<Vec<_>>();
+        self.next_tile = end;
+
+        let mut count = 0;
+        for (tile_film, samples, ray_stats) in tile_films {
+            self.ray_stats += ray_stats;
+            if samples > 0 {
+                self.film.merge(&tile_film);
+                count += samples;
+            }
+        }
+        count
+    }
+
+    /// Returns the current image with the exposure of the camera and gamma correction applied
+    fn image(&self) -> RenderedImage {
+        let scene = self.context.scene;
+        let exposure = scene.camera.exposure();
+        let mut pixel_colors = self.film.pixel_colors();
+        for pixel_color in pixel_colors.iter_mut() {
+            *pixel_color = *pixel_color * exposure;
+            // Gamma adjustment, negative lobes of the filter can result in slightly negative colors
+            pixel_color.r = pixel_color.r.max(0.0).sqrt();
+            pixel_color.g = pixel_color.g.max(0.0).sqrt();
+            pixel_color.b = pixel_color.b.max(0.0).sqrt();
+            pixel_color.clamp();
+        }
+        let mut sample_counts = vec![0; scene.width * scene.height];
+        for tile in &self.tiles {
+            for (idx, statistics) in tile.statistics.iter().enumerate() {
+                let i = tile.x + idx % tile.width;
+                let j = tile.y + idx / tile.width;
+                sample_counts[(scene.height - 1 - j) * scene.width + i] = statistics.count();
+            }
+        }
+        RenderedImage {
+            pixel_colors,
+            sample_counts,
+        }
+    }
+
+    /// Returns the film and the statistics of all tiles
+    fn checkpoint(&self) -> CameraCheckpoint {
+        CameraCheckpoint {
+            next_tile: self.next_tile,
+            film: self.film.pixels().to_vec(),
+            statistics: self
+                .tiles
+                .iter()
+                .flat_map(|tile| tile.statistics.iter().copied())
+                .collect(),
+        }
+    }
+
+    /// Replaces the film and the statistics of all tiles by the ones of a checkpoint
+    fn restore(&mut self, checkpoint: CameraCheckpoint) -> Result<(), String> {
+        let pixel_count: usize = self.tiles.iter().map(|tile| tile.statistics.len()).sum();
+        if checkpoint.statistics.len() != pixel_count || checkpoint.next_tile > self.tiles.len() {
+            return Err("the checkpoint has a different image size".to_string());
+        }
+        if !self.film.restore(checkpoint.film) {
+            return Err("the checkpoint has a different image size".to_string());
+        }
+        let mut statistics = checkpoint.statistics.into_iter();
+        for tile in self.tiles.iter_mut() {
+            for pixel in tile.statistics.iter_mut() {
+                *pixel = statistics.next().unwrap();
+            }
+        }
+        self.next_tile = checkpoint.next_tile;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::raytracer::{
+        adaptive::AdaptiveSampling,
+        anti_aliasing::SuperSampling,
+        camera::Camera,
+        film::Filter,
+        integrator::{Integrator, IntegratorType},
+        progressive::{Progressive, WriteInterval},
+        sampler::SamplerType,
+        scene::{Scene, StereoConfig, StereoLayout},
+    };
+
+    use super::{render, RenderEvent, RenderSettings, RenderedImage, Renderer, Tile, TILE_SIZE};
+
+    fn create_test_scene() -> Scene {
+        let yaml = "
+width: 8
+height: 6
+background: [0.5, 0.7, 1.0]
+camera:
+  eye: [0, 1, 5]
+  look_at: [0, 0.5, 0]
+  up: [0, 1, 0]
+  fovy: 45
+  aperture: 0.1
+lights:
+  - type: Point
+    position: [2, 4, 2]
+    color: [1, 1, 1]
+objects:
+  - type: Plane
+    center: [0, 0, 0]
+    normal: [0, 1, 0]
+    material:
+      type: Lambertian
+      albedo: [0.5, 0.5, 0.5]
+  - type: Sphere
+    center: [0, 1, 0]
+    radius: 1
+    material:
+      type: Dieletrics
+      tint: [1, 1, 1]
+      refraction_index: 1.5
+";
+        let mut scene: Scene = serde_yaml::from_str(yaml).unwrap();
+        scene.precompute();
+        scene
+    }
+
+    /// Returns settings for a single sample per pixel, which the tests override as needed
+    fn test_settings() -> RenderSettings {
+        RenderSettings {
+            ssaa: SuperSampling::Jitter(1),
+            ..RenderSettings::default()
+        }
+    }
+
+    fn render_camera(
+        scene: &Scene,
+        settings: &RenderSettings,
+        integrator: &dyn Integrator,
+    ) -> RenderedImage {
+        let camera = Camera::new(&scene.camera, scene.width, scene.height);
+        let cameras = [camera];
+        let mut renderer = Renderer::new(scene, &cameras, settings, integrator);
+        renderer.run(&mut |_, _| {});
+        renderer.images().pop().unwrap()
+    }
+
+    #[test]
+    fn test_tiles_cover_image() {
+        let (width, height) = (2 * TILE_SIZE + 3, TILE_SIZE + 1);
+        let tiles = Tile::split(width, height);
+        assert_eq!(tiles.len(), 6);
+        let mut covered = vec![0; width * height];
+        for tile in &tiles {
+            assert!(tile.width <= TILE_SIZE && tile.statistics.len() <= TILE_SIZE * TILE_SIZE);
+            for idx in 0..tile.statistics.len() {
+                covered[(tile.y + idx / tile.width) * width + tile.x + idx % tile.width] += 1;
+            }
+        }
+        assert!(covered.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn test_seeded_render_is_reproducible() {
+        let scene = create_test_scene();
+        let integrator = IntegratorType::Path.build(8, 3);
+        for sampler in [SamplerType::Independent, SamplerType::Sobol] {
+            let settings = |seed| RenderSettings {
+                ssaa: SuperSampling::Jitter(2),
+                filter: Filter::Gaussian(1.5),
+                sampler,
+                seed,
+                ..test_settings()
+            };
+            let first = render_camera(&scene, &settings(7), integrator.as_ref());
+            let second = render_camera(&scene, &settings(7), integrator.as_ref());
+            let other = render_camera(&scene, &settings(8), integrator.as_ref());
+            assert!(first.pixel_colors == second.pixel_colors);
+            assert!(first.pixel_colors != other.pixel_colors);
+        }
+    }
+
+    #[test]
+    fn test_render_is_independent_of_threads() {
+        let mut scene = create_test_scene();
+        // several tiles whose samples are splatted across the tile borders by the filter
+        scene.width = 3 * TILE_SIZE + 5;
+        scene.height = 2 * TILE_SIZE + 3;
+        let integrator = IntegratorType::Path.build(8, 3);
+        for sampler in [SamplerType::Independent, SamplerType::Sobol] {
+            let settings = |threads| RenderSettings {
+                ssaa: SuperSampling::Jitter(2),
+                filter: Filter::Gaussian(1.5),
+                sampler,
+                seed: 7,
+                threads,
+                ..test_settings()
+            };
+            let single = render(&scene, &settings(1), integrator.as_ref());
+            let multi = render(&scene, &settings(4), integrator.as_ref());
+            assert!(single.pixel_colors == multi.pixel_colors);
+            assert_eq!(single.sample_counts, multi.sample_counts);
+        }
+    }
+
+    #[test]
+    fn test_adaptive_sample_counts() {
+        let scene = create_test_scene();
+        let integrator = IntegratorType::Path.build(8, 3);
+        let settings = RenderSettings {
+            adaptive: Some(AdaptiveSampling {
+                min_samples: 4,
+                max_samples: 64,
+                threshold: 0.01,
+            }),
+            ..test_settings()
+        };
+        let rendered = render_camera(&scene, &settings, integrator.as_ref());
+        let counts = rendered.sample_counts;
+        assert_eq!(counts.len(), scene.width * scene.height);
+        assert!(counts.iter().all(|&count| (4..=64).contains(&count)));
+        // the sky in the top row is constant while the noisy floor and glass sphere need more samples
+        assert_eq!(counts[0], 4);
+        assert!(counts.contains(&64));
+    }
+
+    #[test]
+    fn test_progressive_passes() {
+        let scene = create_test_scene();
+        let integrator = IntegratorType::Path.build(8, 3);
+        let mut settings = RenderSettings {
+            ssaa: SuperSampling::Jitter(4),
+            sampler: SamplerType::Sobol,
+            progressive: Some(Progressive {
+                target_spp: Some(5),
+                time_limit: None,
+                write_interval: WriteInterval::Passes(2),
+            }),
+            ..test_settings()
+        };
+        let cameras = [Camera::new(&scene.camera, scene.width, scene.height)];
+        let mut renderer = Renderer::new(&scene, &cameras, &settings, integrator.as_ref());
+        let mut writes = 0;
+        let complete = renderer.run(&mut |renderer, event| {
+            if event == RenderEvent::ProgressDue {
+                assert_eq!(renderer.images().len(), 1);
+                writes += 1;
+            }
+        });
+        assert!(complete);
+        let rendered = renderer.images().pop().unwrap();
+        // the image is written after the second and fourth pass, the final image is returned instead
+        assert_eq!(writes, 2);
+        assert!(rendered.sample_counts.iter().all(|&count| count == 5));
+
+        // converged pixels are skipped and the render stops once all pixels converged
+        settings.adaptive = Some(AdaptiveSampling {
+            min_samples: 2,
+            max_samples: 4,
+            threshold: 0.01,
+        });
+        settings.progressive = Some(Progressive {
+            target_spp: Some(100),
+            ..settings.progressive.unwrap()
+        });
+        let rendered = render_camera(&scene, &settings, integrator.as_ref());
+        let counts = rendered.sample_counts;
+        assert!(counts.iter().all(|&count| (2..=4).contains(&count)));
+        assert!(counts.contains(&2) && counts.contains(&4));
+
+        // a render stopped by its time limit before reaching the target isn't complete
+        settings.adaptive = None;
+        settings.progressive = Some(Progressive {
+            target_spp: Some(100),
+            time_limit: Some(Duration::ZERO),
+            write_interval: WriteInterval::Passes(1),
+        });
+        let mut renderer = Renderer::new(&scene, &cameras, &settings, integrator.as_ref());
+        assert!(!renderer.run(&mut |_, _| {}));
+        assert_eq!(renderer.passes, 1);
+    }
+
+    #[test]
+    fn test_ray_stats_and_progress() {
+        let scene = create_test_scene();
+        let integrator = IntegratorType::Path.build(8, 3);
+        let settings = RenderSettings {
+            ssaa: SuperSampling::Jitter(2),
+            ..test_settings()
+        };
+        let cameras = [Camera::new(&scene.camera, scene.width, scene.height)];
+        let mut renderer = Renderer::new(&scene, &cameras, &settings, integrator.as_ref());
+        let mut reported = Vec::new();
+        renderer.run(&mut |renderer, _| reported.push(renderer.progress()));
+        // the test scene consists of a single tile
+        assert_eq!(reported.len(), 1);
+        assert_eq!((reported[0].passes, reported[0].tiles_done), (0, 1));
+
+        let ray_stats = renderer.ray_stats();
+        assert_eq!(
+            ray_stats.primary_rays as usize,
+            4 * scene.width * scene.height
+        );
+        // glass and the diffuse floor scatter rays and the floor samples the point light
+        assert!(ray_stats.secondary_rays() > 0 && ray_stats.shadow_rays > 0);
+        // every ray is at least tested against the unbounded plane
+        assert!(ray_stats.intersection_tests >= ray_stats.total_rays());
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let mut scene = create_test_scene();
+        // several tiles so the render can be interrupted in the middle of a pass
+        scene.width = 2 * TILE_SIZE + 3;
+        scene.height = TILE_SIZE + 1;
+        let cameras = [Camera::new(&scene.camera, scene.width, scene.height)];
+        let integrator = IntegratorType::Path.build(4, 2);
+        let progressive = Progressive {
+            target_spp: Some(3),
+            time_limit: None,
+            write_interval: WriteInterval::Passes(1),
+        };
+        for progressive in [None, Some(progressive)] {
+            let settings_with = |filter, sampler| RenderSettings {
+                ssaa: SuperSampling::Jitter(2),
+                filter,
+                sampler,
+                seed: 3,
+                progressive,
+                ..test_settings()
+            };
+            let settings = settings_with(Filter::Gaussian(1.5), SamplerType::Sobol);
+            let mut renderer = Renderer::new(&scene, &cameras, &settings, integrator.as_ref());
+            renderer.chunk_size = 1;
+            let mut checkpoint = None;
+            let mut chunks = 0;
+            renderer.run(&mut |renderer, event| {
+                if event == RenderEvent::TilesRendered {
+                    chunks += 1;
+                    // in the middle of the first pass and for progressive renders of the second pass
+                    if chunks == 8 || (checkpoint.is_none() && chunks == 2) {
+                        checkpoint = Some(renderer.checkpoint(42));
+                    }
+                }
+            });
+            let uninterrupted = renderer.images().pop().unwrap();
+
+            let mut resumed = Renderer::new(&scene, &cameras, &settings, integrator.as_ref());
+            // checkpoints of other scenes are rejected
+            assert!(resumed.restore(renderer.checkpoint(7), 42).is_err());
+            let checkpoint = checkpoint.unwrap();
+            assert_eq!(checkpoint.cameras[0].next_tile, 2);
+            // as are checkpoints rendered with other settings, whose samples can't be mixed
+            for other_settings in [
+                settings_with(Filter::Box(0.5), SamplerType::Sobol),
+                settings_with(Filter::Gaussian(1.5), SamplerType::Independent),
+            ] {
+                let mut other =
+                    Renderer::new(&scene, &cameras, &other_settings, integrator.as_ref());
+                assert!(other.restore(renderer.checkpoint(42), 42).is_err());
+            }
+            let other_integrator = IntegratorType::Path.build(5, 2);
+            let mut other = Renderer::new(&scene, &cameras, &settings, other_integrator.as_ref());
+            assert!(other.restore(renderer.checkpoint(42), 42).is_err());
+
+            resumed.restore(checkpoint, 42).unwrap();
+            resumed.run(&mut |_, _| {});
+            let resumed = resumed.images().pop().unwrap();
+            assert!(resumed.pixel_colors == uninterrupted.pixel_colors);
+            assert!(resumed.sample_counts == uninterrupted.sample_counts);
+        }
+    }
+
+    #[test]
+    fn test_render_framebuffer() {
+        let mut scene = create_test_scene();
+        let integrator = IntegratorType::Direct.build(8, 3);
+        let settings = RenderSettings {
+            threads: 2,
+            ..test_settings()
+        };
+        let framebuffer = render(&scene, &settings, integrator.as_ref());
+        assert_eq!((framebuffer.width, framebuffer.height), (8, 6));
+        assert_eq!(framebuffer.pixel_colors.len(), 8 * 6);
+        assert_eq!(framebuffer.to_rgb8().len(), 3 * 8 * 6);
+        assert!(framebuffer.sample_counts.iter().all(|&count| count == 1));
+        // the sky is visible in the top left corner
+        assert!(!framebuffer.pixel(0, 0).is_black());
+
+        scene.camera.stereo = Some(StereoConfig {
+            interocular_distance: 0.065,
+            convergence: None,
+            layout: StereoLayout::OverUnder,
+        });
+        let framebuffer = render(&scene, &settings, integrator.as_ref());
+        assert_eq!((framebuffer.width, framebuffer.height), (8, 12));
+        assert_eq!(framebuffer.pixel_colors.len(), 8 * 12);
+    }
 }
@@ -0,0 +1,650 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::sync::OnceLock;
+
+/// Source of the random numbers of a pixel sample.
+/// Every pixel sample requests its numbers in the same order (pixel position, lens, time and then light and BSDF samples
+/// for every bounce), so each request maps to a fixed dimension of the underlying sequence.
+/// Low-discrepancy samplers distribute the samples of a pixel evenly in every dimension, which converges faster than
+/// independent random numbers.
+pub trait Sampler {
+    /// Starts the `index`-th sample of the pixel, subsequent requests start at the first dimension again
+    ///
+    /// # Arguments
+    ///
+    /// * `x` coordinate of the pixel on the x-axis
+    /// * `y` coordinate of the pixel on the y-axis
+    /// * `index` index of the sample in the pixel
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize);
+
+    /// Returns the next dimension of the current sample in `[0, 1)`
+    fn get_1d(&mut self) -> f64;
+
+    /// Returns the next two dimensions of the current sample in `[0, 1)²`
+    fn get_2d(&mut self) -> (f64, f64);
+
+    /// Returns the position of the current sample on the image.
+    /// Low-discrepancy samplers place the sample in the pixel themselves, the independent sampler keeps the position
+    /// on the super sampling grid.
+    ///
+    /// # Arguments
+    ///
+    /// * `grid_position` position of the sample on the super sampling grid of the pixel
+    fn get_pixel_2d(&mut self, grid_position: (f64, f64)) -> (f64, f64);
+}
+
+/// Enum representing the samplers which can be selected on the command line
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SamplerType {
+    /// Independent uniform random numbers
+    Independent,
+    /// Halton sequence with a random rotation per pixel
+    Halton,
+    /// Owen scrambled Sobol (0, 2)-sequence with a random scramble per pixel
+    Sobol,
+    /// Owen scrambled Sobol sequence shared by all pixels and rotated by a blue-noise mask,
+    /// which distributes the remaining error as blue noise over the image
+    BlueNoise,
+}
+
+impl SamplerType {
+    /// Creates a sampler of this type.
+    /// The numbers only depend on the seed, the pixel, the sample index and the dimension, so a render is reproducible
+    /// no matter in which order or on which thread the samples are taken.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` seed of the random streams, different seeds result in different (but equally distributed) noise
+    pub fn build(&self, seed: u64) -> Box<dyn Sampler> {
+        let state = SampleState::new(seed);
+        match self {
+            SamplerType::Independent => Box::new(IndependentSampler { state }),
+            SamplerType::Halton => Box::new(HaltonSampler { state }),
+            SamplerType::Sobol => Box::new(SobolSampler { state }),
+            SamplerType::BlueNoise => Box::new(BlueNoiseSampler { state }),
+        }
+    }
+}
+
+/// Error Type for Decoding a SamplerType-Variant from a String
+pub struct SamplerDecodeError {
+    error: String,
+}
+
+impl SamplerDecodeError {
+    fn new(error: String) -> SamplerDecodeError {
+        SamplerDecodeError { error }
+    }
+}
+
+impl std::error::Error for SamplerDecodeError {}
+
+impl std::fmt::Display for SamplerDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing sampler argument: {}", self.error)
+    }
+}
+
+impl std::fmt::Debug for SamplerDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error parsing sampler argument: {}", self.error)
+    }
+}
+
+impl std::str::FromStr for SamplerType {
+    type Err = SamplerDecodeError;
+
+    fn from_str(s: &str) -> Result<SamplerType, SamplerDecodeError> {
+        match s {
+            "independent" => Ok(SamplerType::Independent),
+            "halton" => Ok(SamplerType::Halton),
+            "sobol" => Ok(SamplerType::Sobol),
+            "bluenoise" => Ok(SamplerType::BlueNoise),
+            _ => Err(SamplerDecodeError::new("unknown sampler".to_string())),
+        }
+    }
+}
+
+/// Sampler returning independent uniform random numbers for every request,
+/// which are generated by hashing the seed, pixel, sample index and dimension
+pub struct IndependentSampler {
+    state: SampleState,
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize) {
+        self.state.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.state.advance(1);
+        to_unit(self.state.sample_hash(dimension))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.state.advance(2);
+        (
+            to_unit(self.state.sample_hash(dimension)),
+            to_unit(self.state.sample_hash(dimension + 1)),
+        )
+    }
+
+    fn get_pixel_2d(&mut self, grid_position: (f64, f64)) -> (f64, f64) {
+        grid_position
+    }
+}
+
+/// Seed, pixel, index and next dimension of the sample currently generated by a sampler
+struct SampleState {
+    seed: u64,
+    x: usize,
+    y: usize,
+    index: usize,
+    dimension: usize,
+}
+
+impl SampleState {
+    fn new(seed: u64) -> SampleState {
+        SampleState {
+            seed,
+            x: 0,
+            y: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn start(&mut self, x: usize, y: usize, index: usize) {
+        self.x = x;
+        self.y = y;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    /// Returns the next dimension and advances by `count` dimensions
+    fn advance(&mut self, count: usize) -> usize {
+        let dimension = self.dimension;
+        self.dimension += count;
+        dimension
+    }
+
+    /// Returns a hash of the seed, the pixel and the given dimension
+    fn pixel_hash(&self, dimension: usize) -> u64 {
+        hash(&[self.seed, self.x as u64, self.y as u64, dimension as u64])
+    }
+
+    /// Returns a hash of the seed, the pixel, the sample index and the given dimension
+    fn sample_hash(&self, dimension: usize) -> u64 {
+        hash(&[
+            self.seed,
+            self.x as u64,
+            self.y as u64,
+            self.index as u64,
+            dimension as u64,
+        ])
+    }
+
+    /// Returns the position of the current sample for an offset in `[0, 1)²` inside the pixel
+    fn pixel_position(&self, u: (f64, f64)) -> (f64, f64) {
+        (self.x as f64 + u.0, self.y as f64 + u.1)
+    }
+}
+
+/// First primes used as bases of the Halton sequence, further dimensions fall back to hashed random numbers
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+/// Sampler generating the Halton sequence with the `n`-th prime as base of the `n`-th dimension.
+/// Every pixel uses the same points with a random toroidal shift (Cranley-Patterson rotation) per dimension.
+pub struct HaltonSampler {
+    state: SampleState,
+}
+
+impl HaltonSampler {
+    fn sample(&self, dimension: usize) -> f64 {
+        if dimension >= PRIMES.len() {
+            return to_unit(self.state.sample_hash(dimension));
+        }
+        let rotation = to_unit(self.state.pixel_hash(dimension));
+        let value = radical_inverse(self.state.index as u64, PRIMES[dimension]) + rotation;
+        wrap_unit(value)
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize) {
+        self.state.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.state.advance(1);
+        self.sample(dimension)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.state.advance(2);
+        (self.sample(dimension), self.sample(dimension + 1))
+    }
+
+    fn get_pixel_2d(&mut self, _grid_position: (f64, f64)) -> (f64, f64) {
+        let u = self.get_2d();
+        self.state.pixel_position(u)
+    }
+}
+
+/// Sampler padding Owen scrambled two dimensional Sobol (0, 2)-sequences.
+/// Every request uses its own scramble of the sequence and a shuffled order of the samples, so dimensions are
+/// decorrelated from each other and from neighbouring pixels.
+pub struct SobolSampler {
+    state: SampleState,
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize) {
+        self.state.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.state.advance(1);
+        let seed = self.state.pixel_hash(dimension);
+        sobol_1d(self.state.index as u32, seed)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.state.advance(2);
+        let seed = self.state.pixel_hash(dimension);
+        sobol_2d(self.state.index as u32, seed)
+    }
+
+    fn get_pixel_2d(&mut self, _grid_position: (f64, f64)) -> (f64, f64) {
+        let u = self.get_2d();
+        self.state.pixel_position(u)
+    }
+}
+
+/// Width and height of the tiled blue-noise mask
+const BLUE_NOISE_SIZE: usize = 64;
+
+/// Sampler using the same Owen scrambled Sobol points in every pixel, which are rotated per pixel and dimension by the
+/// value of a blue-noise mask. Neighbouring pixels therefore receive very different rotations and their errors
+/// form blue noise, which is far less visible than white noise at low sample counts.
+pub struct BlueNoiseSampler {
+    state: SampleState,
+}
+
+impl BlueNoiseSampler {
+    /// Returns the rotation of the pixel for a dimension by looking up the mask with a dimension dependent offset
+    fn rotation(&self, dimension: usize) -> f64 {
+        let mask = blue_noise_mask();
+        let offset = hash(&[self.state.seed, dimension as u64]) as usize;
+        let x = (self.state.x + offset) % BLUE_NOISE_SIZE;
+        let y = (self.state.y + (offset >> 32)) % BLUE_NOISE_SIZE;
+        mask[y * BLUE_NOISE_SIZE + x]
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize) {
+        self.state.start(x, y, index);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.state.advance(1);
+        let u = sobol_1d(
+            self.state.index as u32,
+            hash(&[self.state.seed, dimension as u64]),
+        );
+        wrap_unit(u + self.rotation(dimension))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.state.advance(2);
+        let u = sobol_2d(
+            self.state.index as u32,
+            hash(&[self.state.seed, dimension as u64]),
+        );
+        (
+            wrap_unit(u.0 + self.rotation(dimension)),
+            wrap_unit(u.1 + self.rotation(dimension + 1)),
+        )
+    }
+
+    fn get_pixel_2d(&mut self, _grid_position: (f64, f64)) -> (f64, f64) {
+        let u = self.get_2d();
+        self.state.pixel_position(u)
+    }
+}
+
+/// Returns the blue-noise mask shared by all blue-noise samplers, which is generated on first use
+fn blue_noise_mask() -> &'static [f64] {
+    static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+    MASK.get_or_init(|| void_and_cluster(BLUE_NOISE_SIZE, 1.5))
+}
+
+/// Generates a tileable blue-noise mask of `size * size` values in `[0, 1)` with the void-and-cluster method.
+/// Pixels are ranked by repeatedly removing the tightest cluster from an evenly distributed initial pattern and
+/// filling the largest void afterwards, where clusters and voids are found by a gaussian energy of width `sigma`.
+fn void_and_cluster(size: usize, sigma: f64) -> Vec<f64> {
+    let count = size * size;
+    // gaussian of the toroidal distance for every offset between two pixels
+    let energy_lut: Vec<f64> = (0..count)
+        .map(|idx| {
+            let dx = (idx % size).min(size - idx % size) as f64;
+            let dy = (idx / size).min(size - idx / size) as f64;
+            f64::exp(-(dx * dx + dy * dy) / (2.0 * sigma * sigma))
+        })
+        .collect();
+    let mut pattern = BinaryPattern {
+        size,
+        ones: vec![false; count],
+        energy: vec![0.0; count],
+        energy_lut,
+    };
+
+    // initial pattern of randomly placed points, relaxed by moving the tightest cluster into the largest void
+    let mut rng = StdRng::seed_from_u64(0);
+    let initial = count / 10;
+    let mut placed = 0;
+    while placed < initial {
+        let idx = rng.gen_range(0..count);
+        if !pattern.ones[idx] {
+            pattern.toggle(idx);
+            placed += 1;
+        }
+    }
+    loop {
+        let cluster = pattern.tightest_cluster();
+        pattern.toggle(cluster);
+        let void = pattern.largest_void();
+        pattern.toggle(void);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; count];
+    let prototype = pattern.ones.clone();
+    let prototype_energy = pattern.energy.clone();
+    for rank in (0..initial).rev() {
+        let cluster = pattern.tightest_cluster();
+        pattern.toggle(cluster);
+        ranks[cluster] = rank;
+    }
+    pattern.ones = prototype;
+    pattern.energy = prototype_energy;
+    for rank in initial..count {
+        let void = pattern.largest_void();
+        pattern.toggle(void);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .into_iter()
+        .map(|rank| (rank as f64 + 0.5) / count as f64)
+        .collect()
+}
+
+/// Binary pattern on a torus with the gaussian energy every pixel receives from all set pixels
+struct BinaryPattern {
+    size: usize,
+    ones: Vec<bool>,
+    energy: Vec<f64>,
+    energy_lut: Vec<f64>,
+}
+
+impl BinaryPattern {
+    /// Flips the pixel and updates the energy of all pixels
+    fn toggle(&mut self, idx: usize) {
+        self.ones[idx] = !self.ones[idx];
+        let sign = if self.ones[idx] { 1.0 } else { -1.0 };
+        let (x, y) = (idx % self.size, idx / self.size);
+        for (other, energy) in self.energy.iter_mut().enumerate() {
+            let dx = (other % self.size + self.size - x) % self.size;
+            let dy = (other / self.size + self.size - y) % self.size;
+            *energy += sign * self.energy_lut[dy * self.size + dx];
+        }
+    }
+
+    /// Returns the set pixel with the highest energy
+    fn tightest_cluster(&self) -> usize {
+        self.extreme(true, |a, b| a > b)
+    }
+
+    /// Returns the unset pixel with the lowest energy
+    fn largest_void(&self) -> usize {
+        self.extreme(false, |a, b| a < b)
+    }
+
+    fn extreme(&self, set: bool, better: fn(f64, f64) -> bool) -> usize {
+        let mut best: Option<usize> = None;
+        for (idx, &energy) in self.energy.iter().enumerate() {
+            if self.ones[idx] != set {
+                continue;
+            }
+            let improves = match best {
+                Some(b) => better(energy, self.energy[b]),
+                None => true,
+            };
+            if improves {
+                best = Some(idx);
+            }
+        }
+        best.unwrap()
+    }
+}
+
+/// Returns the radical inverse of `index` in the given base, i.e. its digits mirrored at the decimal point
+fn radical_inverse(mut index: u64, base: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_n = 1.0;
+    let mut reversed = 0;
+    while index > 0 {
+        let next = index / base;
+        reversed = reversed * base + (index - next * base);
+        inv_base_n *= inv_base;
+        index = next;
+    }
+    f64::min(reversed as f64 * inv_base_n, ONE_MINUS_EPSILON)
+}
+
+/// Returns the `index`-th point of an Owen scrambled van der Corput sequence in a shuffled order
+fn sobol_1d(index: u32, seed: u64) -> f64 {
+    let index = nested_uniform_scramble(index, seed as u32);
+    to_unit_u32(nested_uniform_scramble(
+        index.reverse_bits(),
+        (seed >> 32) as u32,
+    ))
+}
+
+/// Returns the `index`-th point of the first two Owen scrambled Sobol dimensions in a shuffled order
+fn sobol_2d(index: u32, seed: u64) -> (f64, f64) {
+    let index = nested_uniform_scramble(index, seed as u32);
+    let seed = hash(&[seed]);
+    (
+        to_unit_u32(nested_uniform_scramble(index.reverse_bits(), seed as u32)),
+        to_unit_u32(nested_uniform_scramble(
+            sobol_second_dimension(index),
+            (seed >> 32) as u32,
+        )),
+    )
+}
+
+/// Evaluates the generator matrix of the second Sobol dimension
+fn sobol_second_dimension(mut index: u32) -> u32 {
+    let mut result = 0;
+    let mut direction = 1 << 31;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+/// Owen scrambling of the bits of `x` by the hash based permutation of Laine and Karras
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x.reverse_bits()
+}
+
+/// Largest double below one, which samples are clamped to
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+/// Hashes the values into 64 bits by chaining the splitmix64 finalizer
+pub fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e3779b97f4a7c15, |h, &v| {
+        let mut z = (h ^ v).wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    })
+}
+
+/// Maps the upper 53 bits of a hash to `[0, 1)`
+pub fn to_unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+fn to_unit_u32(bits: u32) -> f64 {
+    bits as f64 * (1.0 / (1u64 << 32) as f64)
+}
+
+/// Wraps a value of `[0, 2)` back into `[0, 1)`
+fn wrap_unit(value: f64) -> f64 {
+    if value >= 1.0 {
+        f64::min(value - 1.0, ONE_MINUS_EPSILON)
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{radical_inverse, sobol_2d, void_and_cluster, Sampler, SamplerType};
+
+    #[test]
+    fn test_radical_inverse() {
+        assert_eq!(radical_inverse(0, 2), 0.0);
+        assert_eq!(radical_inverse(1, 2), 0.5);
+        assert_eq!(radical_inverse(3, 2), 0.75);
+        assert!((radical_inverse(5, 3) - 7.0 / 9.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_sobol_stratification() {
+        // the first 16 points of a (0, 2)-sequence fill every elementary interval of area 1/16 exactly once
+        for seed in [0, 7, 12345] {
+            let points: Vec<(f64, f64)> = (0..16).map(|i| sobol_2d(i, seed)).collect();
+            for (columns, rows) in [(16, 1), (8, 2), (4, 4), (2, 8), (1, 16)] {
+                let mut cells = [0; 16];
+                for (x, y) in &points {
+                    let cell = (y * rows as f64) as usize * columns + (x * columns as f64) as usize;
+                    cells[cell] += 1;
+                }
+                assert!(cells.iter().all(|&c| c == 1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_samplers_in_unit_interval() {
+        for sampler_type in [
+            SamplerType::Independent,
+            SamplerType::Halton,
+            SamplerType::Sobol,
+            SamplerType::BlueNoise,
+        ] {
+            let mut sampler = sampler_type.build(0);
+            for index in 0..16 {
+                sampler.start_pixel_sample(3, 5, index);
+                let (x, y) = sampler.get_pixel_2d((3.25, 5.75));
+                assert!((3.0..4.0).contains(&x) && (5.0..6.0).contains(&y));
+                for _ in 0..100 {
+                    assert!((0.0..1.0).contains(&sampler.get_1d()));
+                    let (u, v) = sampler.get_2d();
+                    assert!((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_samplers_consistent_dimensions() {
+        for sampler_type in [
+            SamplerType::Independent,
+            SamplerType::Halton,
+            SamplerType::Sobol,
+            SamplerType::BlueNoise,
+        ] {
+            let mut sampler = sampler_type.build(0);
+            sampler.start_pixel_sample(1, 2, 3);
+            let first = (sampler.get_1d(), sampler.get_2d());
+            sampler.start_pixel_sample(1, 2, 3);
+            assert_eq!((sampler.get_1d(), sampler.get_2d()), first);
+            sampler.start_pixel_sample(1, 2, 4);
+            assert_ne!((sampler.get_1d(), sampler.get_2d()), first);
+
+            // a fresh sampler with the same seed repeats the numbers, another seed doesn't
+            let mut same_seed = sampler_type.build(0);
+            same_seed.start_pixel_sample(1, 2, 3);
+            assert_eq!((same_seed.get_1d(), same_seed.get_2d()), first);
+            let mut other_seed = sampler_type.build(1);
+            other_seed.start_pixel_sample(1, 2, 3);
+            assert_ne!((other_seed.get_1d(), other_seed.get_2d()), first);
+
+            // dimensions of deep paths, beyond the bases of the Halton sequence, still differ between samples
+            let deep_dimensions = |sampler: &mut dyn Sampler, index| {
+                sampler.start_pixel_sample(1, 2, index);
+                (0..100).map(|_| sampler.get_1d()).collect::<Vec<_>>()
+            };
+            let deep = deep_dimensions(sampler.as_mut(), 3);
+            assert_eq!(deep_dimensions(sampler.as_mut(), 3), deep);
+            let other_index = deep_dimensions(sampler.as_mut(), 4);
+            assert!((64..100).all(|dimension| other_index[dimension] != deep[dimension]));
+        }
+    }
+
+    #[test]
+    fn test_blue_noise_mask() {
+        let mask = void_and_cluster(16, 1.5);
+        let mut sorted = mask.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        // every rank is used exactly once
+        for (rank, value) in sorted.iter().enumerate() {
+            assert_eq!(*value, (rank as f64 + 0.5) / 256.0);
+        }
+        // the lowest values are spread out instead of clustering
+        let low: Vec<usize> = (0..256).filter(|&i| mask[i] < 16.0 / 256.0).collect();
+        for &a in &low {
+            for &b in &low {
+                let dx = (a % 16)
+                    .abs_diff(b % 16)
+                    .min(16 - (a % 16).abs_diff(b % 16));
+                let dy = (a / 16)
+                    .abs_diff(b / 16)
+                    .min(16 - (a / 16).abs_diff(b / 16));
+                assert!(a == b || dx * dx + dy * dy >= 4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_sampler() {
+        assert_eq!("sobol".parse::<SamplerType>().unwrap(), SamplerType::Sobol);
+        assert_eq!(
+            "bluenoise".parse::<SamplerType>().unwrap(),
+            SamplerType::BlueNoise
+        );
+        assert!("stratified".parse::<SamplerType>().is_err());
+    }
+}
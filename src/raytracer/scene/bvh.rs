@@ -0,0 +1,437 @@
+use crate::{math::Vector3, raytracer::raytrace::Ray};
+
+use super::{
+    intersections::{closest_intersection, IntersectionInfo},
+    mesh::{Mesh, AABB},
+    scene::{Plane, Sphere},
+};
+
+/// Number of primitives up to which a leaf is always created
+const MIN_SPLIT_SIZE: usize = 2;
+/// Maximum number of primitives stored in a single leaf of the BVH
+const MAX_LEAF_SIZE: usize = 8;
+/// Number of buckets used for evaluating the surface area heuristic
+const SAH_BUCKETS: usize = 12;
+/// Cost of traversing an interior node relative to intersecting a primitive
+const TRAVERSAL_COST: f64 = 0.125;
+
+/// Trait for objects which can be enclosed by an axis-aligned bounding box
+pub trait Bounded {
+    /// Returns the AABB enclosing the object or `None` if the object is unbounded (e.g. an infinite plane)
+    fn bounding_box(&self) -> Option<AABB>;
+}
+
+impl Bounded for Sphere {
+    /// Encloses the sphere at its start and end position and therefore its whole path if it moves
+    fn bounding_box(&self) -> Option<AABB> {
+        let radius = Vector3::new(self.radius, self.radius, self.radius);
+        let start = AABB::new(self.center - radius, self.center + radius);
+        Some(start.union(&start.translate(&(self.center_at(1.0) - self.center))))
+    }
+}
+
+impl Bounded for Plane {
+    fn bounding_box(&self) -> Option<AABB> {
+        None
+    }
+}
+
+impl Bounded for Mesh {
+    /// Encloses the mesh at its start and end position and therefore its whole path if it moves
+    fn bounding_box(&self) -> Option<AABB> {
+        self.aabb
+            .as_ref()
+            .map(|aabb| aabb.union(&aabb.translate(&self.offset_at(1.0))))
+    }
+}
+
+/// Bounding volume hierarchy over a set of primitives referenced by their index.
+/// The tree is stored as a flat list of nodes in depth-first order, so the left child of an interior node
+/// always directly follows its parent.
+#[derive(Clone, Debug)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct BvhNode {
+    aabb: AABB,
+    content: BvhContent,
+}
+
+#[derive(Clone, Debug)]
+enum BvhContent {
+    /// Leaf referencing `count` primitives starting at `start` in the index list of the BVH
+    Leaf { start: usize, count: usize },
+    /// Interior node with the index of its right child and the axis along which the primitives were split
+    Interior { right: usize, axis: usize },
+}
+
+/// Primitive information only needed while building the BVH
+struct BuildPrimitive {
+    index: usize,
+    aabb: AABB,
+    centroid: Vector3,
+}
+
+impl Bvh {
+    /// Builds a new BVH by recursively splitting the primitives along the longest axis of their centroids.
+    /// Split positions are chosen by the surface area heuristic (SAH) evaluated over a fixed number of buckets.
+    ///
+    /// # Arguments
+    ///
+    /// * `primitives` List of primitive indices and their corresponding AABBs
+    pub fn new(primitives: Vec<(usize, AABB)>) -> Bvh {
+        let mut build_primitives: Vec<BuildPrimitive> = primitives
+            .into_iter()
+            .map(|(index, aabb)| BuildPrimitive {
+                index,
+                centroid: aabb.centroid(),
+                aabb,
+            })
+            .collect();
+
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            indices: Vec::with_capacity(build_primitives.len()),
+        };
+        if !build_primitives.is_empty() {
+            bvh.build_recursive(&mut build_primitives);
+        }
+        bvh
+    }
+
+    /// Builds the subtree for the given primitives and returns the index of its root node
+    fn build_recursive(&mut self, primitives: &mut [BuildPrimitive]) -> usize {
+        let node_idx = self.nodes.len();
+        let aabb = primitives
+            .iter()
+            .fold(AABB::empty(), |acc, p| acc.union(&p.aabb));
+        let centroid_bounds = primitives
+            .iter()
+            .fold(AABB::empty(), |acc, p| acc.grow(&p.centroid));
+        let axis = centroid_bounds.longest_axis();
+
+        if primitives.len() <= MIN_SPLIT_SIZE
+            || centroid_bounds.max[axis] <= centroid_bounds.min[axis]
+        {
+            self.push_leaf(aabb, primitives);
+            return node_idx;
+        }
+
+        primitives.sort_by(|a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+        let mid = match Bvh::find_sah_split(primitives, &aabb, &centroid_bounds, axis) {
+            Some(mid) => mid,
+            None if primitives.len() <= MAX_LEAF_SIZE => {
+                self.push_leaf(aabb, primitives);
+                return node_idx;
+            }
+            None => primitives.len() / 2,
+        };
+
+        self.nodes.push(BvhNode {
+            aabb,
+            content: BvhContent::Interior { right: 0, axis },
+        });
+        let (left, right) = primitives.split_at_mut(mid);
+        self.build_recursive(left);
+        let right_idx = self.build_recursive(right);
+        self.nodes[node_idx].content = BvhContent::Interior {
+            right: right_idx,
+            axis,
+        };
+        node_idx
+    }
+
+    /// Evaluates the SAH for splits between the buckets along `axis` and returns the number of primitives
+    /// belonging to the left side of the cheapest split. Returns `None` if no split is cheaper than a leaf.
+    ///
+    /// # Arguments
+    ///
+    /// * `primitives` primitives of the node sorted by their centroid along `axis`
+    /// * `aabb` AABB enclosing all primitives
+    /// * `centroid_bounds` AABB enclosing the centroids of all primitives
+    /// * `axis` axis along which to split
+    fn find_sah_split(
+        primitives: &[BuildPrimitive],
+        aabb: &AABB,
+        centroid_bounds: &AABB,
+        axis: usize,
+    ) -> Option<usize> {
+        let min = centroid_bounds.min[axis];
+        let extent = centroid_bounds.max[axis] - min;
+        let bucket_of = |p: &BuildPrimitive| {
+            let bucket = (SAH_BUCKETS as f64 * (p.centroid[axis] - min) / extent) as usize;
+            bucket.min(SAH_BUCKETS - 1)
+        };
+
+        let mut counts = [0usize; SAH_BUCKETS];
+        let mut bounds: Vec<AABB> = (0..SAH_BUCKETS).map(|_| AABB::empty()).collect();
+        for p in primitives {
+            let bucket = bucket_of(p);
+            counts[bucket] += 1;
+            bounds[bucket] = bounds[bucket].union(&p.aabb);
+        }
+
+        // sweep from the right to get the accumulated area and count right of each split
+        let mut right_area = [0.0; SAH_BUCKETS];
+        let mut right_count = [0usize; SAH_BUCKETS];
+        let mut acc_bounds = AABB::empty();
+        let mut acc_count = 0;
+        for i in (1..SAH_BUCKETS).rev() {
+            acc_bounds = acc_bounds.union(&bounds[i]);
+            acc_count += counts[i];
+            right_area[i] = acc_bounds.surface_area();
+            right_count[i] = acc_count;
+        }
+
+        let mut best: Option<(usize, f64)> = None;
+        let mut acc_bounds = AABB::empty();
+        let mut acc_count = 0;
+        for i in 0..SAH_BUCKETS - 1 {
+            acc_bounds = acc_bounds.union(&bounds[i]);
+            acc_count += counts[i];
+            if acc_count == 0 || right_count[i + 1] == 0 {
+                continue;
+            }
+            let cost = TRAVERSAL_COST
+                + (acc_count as f64 * acc_bounds.surface_area()
+                    + right_count[i + 1] as f64 * right_area[i + 1])
+                    / aabb.surface_area();
+            let improves = match best {
+                Some((_, best_cost)) => cost < best_cost,
+                None => true,
+            };
+            if improves {
+                best = Some((acc_count, cost));
+            }
+        }
+
+        match best {
+            Some((mid, cost)) if cost < primitives.len() as f64 => Some(mid),
+            _ => None,
+        }
+    }
+
+    fn push_leaf(&mut self, aabb: AABB, primitives: &[BuildPrimitive]) {
+        let start = self.indices.len();
+        self.indices.extend(primitives.iter().map(|p| p.index));
+        self.nodes.push(BvhNode {
+            aabb,
+            content: BvhContent::Leaf {
+                start,
+                count: primitives.len(),
+            },
+        });
+    }
+
+    /// Traverses the BVH and returns the closest intersection of the ray with any of the primitives.
+    /// Nodes are visited front to back and skipped if they are further away than the closest intersection found so far.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` the ray for which to check intersections
+    /// * `intersect_primitive` function intersecting the ray with the primitive of the given index
+    pub fn intersect<'a, F>(
+        &self,
+        ray: &Ray,
+        mut intersect_primitive: F,
+    ) -> Option<IntersectionInfo<'a>>
+    where
+        F: FnMut(usize) -> Option<IntersectionInfo<'a>>,
+    {
+        let mut closest: Option<IntersectionInfo> = None;
+        if self.nodes.is_empty() {
+            return closest;
+        }
+
+        let mut stack = Vec::with_capacity(32);
+        stack.push(0);
+        while let Some(node_idx) = stack.pop() {
+            let node = &self.nodes[node_idx];
+            let t_max = closest.map_or(f64::MAX, |c| c.t);
+            if node.aabb.intersect_distance(ray, t_max).is_none() {
+                continue;
+            }
+            match node.content {
+                BvhContent::Leaf { start, count } => {
+                    for &idx in &self.indices[start..start + count] {
+                        closest = closest_intersection(closest, intersect_primitive(idx));
+                    }
+                }
+                BvhContent::Interior { right, axis } => {
+                    // push the far child first so the near child is visited first
+                    if ray.direction[axis] < 0.0 {
+                        stack.push(node_idx + 1);
+                        stack.push(right);
+                    } else {
+                        stack.push(right);
+                        stack.push(node_idx + 1);
+                    }
+                }
+            }
+        }
+        closest
+    }
+
+    /// Traverses the BVH and checks if any of the primitives blocks the ray before the distance `max_t`.
+    /// The traversal stops as soon as the first blocking primitive is found.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` the ray for which to check occlusion
+    /// * `max_t` distance along the ray up to which primitives are considered blocking
+    /// * `blocks_primitive` function checking if the primitive of the given index blocks the ray before `max_t`
+    pub fn any_hit<F>(&self, ray: &Ray, max_t: f64, mut blocks_primitive: F) -> bool
+    where
+        F: FnMut(usize) -> bool,
+    {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let mut stack = Vec::with_capacity(32);
+        stack.push(0);
+        while let Some(node_idx) = stack.pop() {
+            let node = &self.nodes[node_idx];
+            if node.aabb.intersect_distance(ray, max_t).is_none() {
+                continue;
+            }
+            match node.content {
+                BvhContent::Leaf { start, count } => {
+                    if self.indices[start..start + count]
+                        .iter()
+                        .any(|&idx| blocks_primitive(idx))
+                    {
+                        return true;
+                    }
+                }
+                BvhContent::Interior { right, .. } => {
+                    stack.push(right);
+                    stack.push(node_idx + 1);
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        math::Vector3,
+        raytracer::{
+            image::Color,
+            raytrace::Ray,
+            scene::{
+                intersections::{closest_intersection, Intersectable},
+                materials::{EmissiveMaterial, Material},
+                mesh::AABB,
+                Sphere,
+            },
+        },
+    };
+
+    use super::{Bounded, Bvh};
+
+    fn create_spheres() -> Vec<Sphere> {
+        let material = Material::Emissive(EmissiveMaterial::new(Color::new(1.0, 1.0, 1.0)));
+        let mut spheres = Vec::new();
+        for x in 0..6 {
+            for y in 0..6 {
+                for z in 0..6 {
+                    spheres.push(Sphere {
+                        center: Vector3::new(x as f64 * 2.0, y as f64 * 1.5, z as f64 * 2.5),
+                        radius: 0.3 + 0.1 * ((x + y + z) % 4) as f64,
+                        material: material.clone(),
+                        motion: None,
+                    });
+                }
+            }
+        }
+        spheres
+    }
+
+    #[test]
+    fn test_bvh_matches_brute_force() {
+        let spheres = create_spheres();
+        let bvh = Bvh::new(
+            spheres
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (i, s.bounding_box().unwrap()))
+                .collect(),
+        );
+
+        let origin = Vector3::new(-3.0, -2.0, -4.0);
+        for i in 0..20 {
+            for j in 0..20 {
+                let direction = Vector3::new(1.0, i as f64 * 0.05, j as f64 * 0.06);
+                let ray = Ray::new(origin, direction);
+
+                let expected = spheres
+                    .iter()
+                    .fold(None, |acc, s| closest_intersection(acc, s.intersect(&ray)));
+                let actual = bvh.intersect(&ray, |idx| spheres[idx].intersect(&ray));
+
+                assert_eq!(expected.is_some(), actual.is_some());
+                if let (Some(expected), Some(actual)) = (expected, actual) {
+                    assert_eq!(expected.t, actual.t);
+                    assert_eq!(expected.point, actual.point);
+                }
+            }
+        }
+    }
+
+    fn any_hit(bvh: &Bvh, spheres: &[Sphere], ray: &Ray, max_t: f64) -> bool {
+        bvh.any_hit(ray, max_t, |idx| spheres[idx].is_occluded(ray, max_t))
+    }
+
+    #[test]
+    fn test_bvh_any_hit() {
+        let spheres = create_spheres();
+        let bvh = Bvh::new(
+            spheres
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (i, s.bounding_box().unwrap()))
+                .collect(),
+        );
+
+        // ray along the first row of spheres, the first one is hit at t = 2.7
+        let ray = Ray::new(Vector3::new(-3.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(any_hit(&bvh, &spheres, &ray, f64::MAX));
+        assert!(any_hit(&bvh, &spheres, &ray, 3.0));
+        assert!(!any_hit(&bvh, &spheres, &ray, 2.5));
+
+        let ray_miss = Ray::new(Vector3::new(-3.0, -5.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(!any_hit(&bvh, &spheres, &ray_miss, f64::MAX));
+    }
+
+    #[test]
+    fn test_empty_bvh() {
+        let bvh = Bvh::new(Vec::new());
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert!(bvh.intersect(&ray, |_| None).is_none());
+    }
+
+    #[test]
+    fn test_bvh_with_nan_bounds() {
+        let spheres = create_spheres();
+        let mut primitives: Vec<_> = spheres
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (i, s.bounding_box().unwrap()))
+            .collect();
+        // e.g. the bounds of a triangle with a degenerate vertex
+        let nan = Vector3::new(f64::NAN, 0.0, 0.0);
+        primitives.push((spheres.len(), AABB::new(nan, nan)));
+        let bvh = Bvh::new(primitives);
+
+        let ray = Ray::new(Vector3::new(-3.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let info = bvh.intersect(&ray, |idx| spheres.get(idx)?.intersect(&ray));
+        assert!((info.unwrap().t - 2.7).abs() < 1e-9);
+    }
+}
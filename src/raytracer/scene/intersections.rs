@@ -1,4 +1,7 @@
-use crate::{math::Vector3, raytracer::raytrace::Ray};
+use crate::{
+    math::Vector3,
+    raytracer::{raytrace::Ray, stats},
+};
 
 use super::{
     materials::Material,
@@ -9,11 +12,24 @@ use super::{
 pub trait Intersectable {
     /// Checks if the ray intersects the object and returns the corresponding `IntersectionInfo` if it does
     /// or `None` otherwise
-    fn intersect(&self, ray: &Ray) -> Option<IntersectionInfo>;
+    fn intersect(&self, ray: &Ray) -> Option<IntersectionInfo<'_>>;
+
+    /// Checks if the object blocks the ray before the distance `max_t`.
+    /// Implementors can override this to stop at the first blocking hit instead of searching for the closest one.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` the ray for which to check occlusion
+    /// * `max_t` distance along the ray up to which hits are considered blocking
+    fn is_occluded(&self, ray: &Ray, max_t: f64) -> bool {
+        self.intersect(ray).is_some_and(|info| info.t < max_t)
+    }
 }
 
 /// Information about a ray-object intersection.
 /// Contains the intersection point, normal, material of the intersected object and the `t` for which the intersection occurs.
+/// The index of the intersected object is only set by intersection queries on the scene, the index of the material
+/// refers to the materials of the intersected object (always `0` for objects with a single material).
 #[derive(Clone, Copy, Debug)]
 pub struct IntersectionInfo<'mat> {
     pub point: Vector3,
@@ -22,10 +38,17 @@ pub struct IntersectionInfo<'mat> {
     pub t: f64,
     pub u: Option<f64>,
     pub v: Option<f64>,
+    pub object_idx: usize,
+    pub material_idx: usize,
 }
 
 impl IntersectionInfo<'_> {
-    pub fn new(point: Vector3, normal: Vector3, material: &Material, t: f64) -> IntersectionInfo {
+    pub fn new(
+        point: Vector3,
+        normal: Vector3,
+        material: &Material,
+        t: f64,
+    ) -> IntersectionInfo<'_> {
         IntersectionInfo {
             point,
             normal,
@@ -33,14 +56,54 @@ impl IntersectionInfo<'_> {
             t,
             u: None,
             v: None,
+            object_idx: 0,
+            material_idx: 0,
+        }
+    }
+
+    /// Returns the normal flipped to the side of the surface the ray arrives from
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` the ray which intersected the object
+    pub fn facing_normal(&self, ray: &Ray) -> Vector3 {
+        if self.normal.dot(&ray.direction) > 0.0 {
+            -self.normal
+        } else {
+            self.normal
+        }
+    }
+}
+
+/// Returns the closer one of two optional intersections. If both are equally far away `a` is returned.
+///
+/// # Arguments
+///
+/// * `a` first intersection to compare
+/// * `b` second intersection to compare
+pub fn closest_intersection<'a>(
+    a: Option<IntersectionInfo<'a>>,
+    b: Option<IntersectionInfo<'a>>,
+) -> Option<IntersectionInfo<'a>> {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            if b.t < a.t {
+                Some(b)
+            } else {
+                Some(a)
+            }
         }
+        (None, b) => b,
+        (a, None) => a,
     }
 }
 
 impl Intersectable for Sphere {
-    fn intersect(&self, ray: &Ray) -> Option<IntersectionInfo> {
+    fn intersect(&self, ray: &Ray) -> Option<IntersectionInfo<'_>> {
+        stats::count_intersection_test();
+        let center = self.center_at(ray.time);
         let dir = ray.direction;
-        let oc = ray.origin - self.center;
+        let oc = ray.origin - center;
 
         let a = dir.sqr_len();
         let b = 2.0 * dir.dot(&oc);
@@ -66,7 +129,7 @@ impl Intersectable for Sphere {
                 return None;
             }
             let intersection_point = ray.at_timestep(intersection_t);
-            let intersection_normal = (intersection_point - self.center) / self.radius;
+            let intersection_normal = (intersection_point - center) / self.radius;
 
             return Some(IntersectionInfo::new(
                 intersection_point,
@@ -80,7 +143,8 @@ impl Intersectable for Sphere {
 }
 
 impl Intersectable for Plane {
-    fn intersect(&self, ray: &Ray) -> Option<IntersectionInfo> {
+    fn intersect(&self, ray: &Ray) -> Option<IntersectionInfo<'_>> {
+        stats::count_intersection_test();
         let dot_nd = self.normal.dot(&ray.direction);
         if f64::abs(dot_nd) < 1e-6 {
             return None;
@@ -128,68 +192,162 @@ fn calculate_determinant(v1: &Vector3, v2: &Vector3, v3: &Vector3) -> f64 {
 impl Intersectable for Mesh {
     /// Intersection testing of a mesh happens in two steps:
     /// - test the AABB of the mesh
-    /// - test each triangle of the mesh and find the closest intersection (if any exist)
+    /// - test the triangles of the mesh and find the closest intersection (if any exist)
     ///
-    /// Triangle intersection is implemented via barycentric coordinates.
-    /// For a triangle constructed by the points `a`, `b`, `c` and a ray with origin `o` and direction `d`
-    /// the equation `o + td = alpha * a + beta * b + (1 - alpha - beta) * c` has to be solved.
-    /// This is done by using Cramers-Rule after rearranging the equation to:
-    /// `[ d | (b-a) | (c-a) ] = (-t, alpha, beta)^T`
-    /// The Matrix on the left hand side is represented as three column vectors.
-    fn intersect(&self, ray: &Ray) -> Option<IntersectionInfo> {
-        let mut result: Option<IntersectionInfo> = None;
+    /// If the BVH of the mesh has been built only triangles in nodes hit by the ray are tested,
+    /// otherwise each triangle of the mesh is tested.
+    /// Moving meshes are intersected by moving the ray into the opposite direction instead.
+    fn intersect(&self, ray: &Ray) -> Option<IntersectionInfo<'_>> {
+        if self.motion.is_some() {
+            let offset = self.offset_at(ray.time);
+            let local_ray = Ray::with_time(ray.origin - offset, ray.direction, ray.time);
+            return self
+                .intersect_static(&local_ray)
+                .map(|info| IntersectionInfo {
+                    point: info.point + offset,
+                    ..info
+                });
+        }
+        self.intersect_static(ray)
+    }
 
+    fn is_occluded(&self, ray: &Ray, max_t: f64) -> bool {
+        if self.motion.is_some() {
+            let offset = self.offset_at(ray.time);
+            let local_ray = Ray::with_time(ray.origin - offset, ray.direction, ray.time);
+            return self.is_occluded_static(&local_ray, max_t);
+        }
+        self.is_occluded_static(ray, max_t)
+    }
+}
+
+impl Mesh {
+    /// Intersects the ray with the mesh at its start position
+    fn intersect_static(&self, ray: &Ray) -> Option<IntersectionInfo<'_>> {
         if let Some(bb) = &self.aabb {
             if !bb.intersect(ray) {
                 return None;
             }
         }
 
-        for triangle in &self.triangles {
-            let pos_idx = triangle.vertex_idx;
-            let a = self.vertex_positions[pos_idx[0]];
-            let b = self.vertex_positions[pos_idx[1]];
-            let c = self.vertex_positions[pos_idx[2]];
-            let ab = b - a;
-            let ac = c - a;
-
-            let res = ray.origin - a;
-            let det_m = calculate_determinant(&ray.direction, &ab, &ac);
-            let det_m_t = calculate_determinant(&res, &ab, &ac);
-            let det_m_a = calculate_determinant(&ray.direction, &res, &ac);
-            let det_m_b = calculate_determinant(&ray.direction, &ab, &res);
-
-            let a = det_m_a / det_m;
-            let b = det_m_b / det_m;
-            let t = -(det_m_t / det_m);
-
-            if a < 0.0 || b < 0.0 || a + b > 1.0 || t < 0.0 {
-                continue;
-            }
-            let normal = ab.cross(&ac).normalized();
-            if result.is_none() || result.unwrap().t > t {
-                let mut info = IntersectionInfo::new(
-                    ray.at_timestep(t),
-                    normal,
-                    &self.materials[triangle.material_idx],
-                    t,
-                );
-                if let Some(tuv_idx) = triangle.uv_idx {
-                    let (u1, v1) = self.uvs[tuv_idx[1]];
-                    let (u2, v2) = self.uvs[tuv_idx[2]];
-                    let (u3, v3) = self.uvs[tuv_idx[0]];
-                    info.u = Some(a * u1 + b * u2 + (1.0 - a - b) * u3);
-                    info.v = Some(a * v1 + b * v2 + (1.0 - a - b) * v3);
-                }
-                result = Some(info);
+        match &self.bvh {
+            Some(bvh) => bvh.intersect(ray, |idx| self.intersect_triangle(ray, idx)),
+            None => (0..self.triangles.len()).fold(None, |result, idx| {
+                closest_intersection(result, self.intersect_triangle(ray, idx))
+            }),
+        }
+    }
+
+    /// Checks if the mesh at its start position blocks the ray before `max_t`
+    fn is_occluded_static(&self, ray: &Ray, max_t: f64) -> bool {
+        if let Some(bb) = &self.aabb {
+            if bb.intersect_distance(ray, max_t).is_none() {
+                return false;
             }
         }
 
-        result
+        let blocks = |idx: usize| {
+            self.intersect_triangle(ray, idx)
+                .is_some_and(|info| info.t < max_t)
+        };
+        match &self.bvh {
+            Some(bvh) => bvh.any_hit(ray, max_t, blocks),
+            None => (0..self.triangles.len()).any(blocks),
+        }
+    }
+
+    /// Intersects the ray with a single triangle of the mesh.
+    ///
+    /// Triangle intersection is implemented via barycentric coordinates.
+    /// For a triangle constructed by the points `a`, `b`, `c` and a ray with origin `o` and direction `d`
+    /// the equation `o + td = alpha * a + beta * b + (1 - alpha - beta) * c` has to be solved.
+    /// This is done by using Cramers-Rule after rearranging the equation to:
+    /// `[ d | (b-a) | (c-a) ] = (-t, alpha, beta)^T`
+    /// The Matrix on the left hand side is represented as three column vectors.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` the ray for which to check the intersection
+    /// * `triangle_idx` index of the triangle in the mesh's triangle list
+    fn intersect_triangle(&self, ray: &Ray, triangle_idx: usize) -> Option<IntersectionInfo<'_>> {
+        stats::count_intersection_test();
+        let triangle = &self.triangles[triangle_idx];
+        let pos_idx = triangle.vertex_idx;
+        let a = self.vertex_positions[pos_idx[0]];
+        let b = self.vertex_positions[pos_idx[1]];
+        let c = self.vertex_positions[pos_idx[2]];
+        let ab = b - a;
+        let ac = c - a;
+
+        let res = ray.origin - a;
+        let det_m = calculate_determinant(&ray.direction, &ab, &ac);
+        // ray parallel to the triangle or degenerated triangle
+        if det_m.abs() < 1e-12 {
+            return None;
+        }
+        let det_m_t = calculate_determinant(&res, &ab, &ac);
+        let det_m_a = calculate_determinant(&ray.direction, &res, &ac);
+        let det_m_b = calculate_determinant(&ray.direction, &ab, &res);
+
+        let a = det_m_a / det_m;
+        let b = det_m_b / det_m;
+        let t = -(det_m_t / det_m);
+
+        if a < 0.0 || b < 0.0 || a + b > 1.0 || t < 1e-5 {
+            return None;
+        }
+        let normal = ab.cross(&ac).normalized();
+        let mut info = IntersectionInfo::new(
+            ray.at_timestep(t),
+            normal,
+            &self.materials[triangle.material_idx],
+            t,
+        );
+        info.material_idx = triangle.material_idx;
+        if let Some(tuv_idx) = triangle.uv_idx {
+            let (u1, v1) = self.uvs[tuv_idx[1]];
+            let (u2, v2) = self.uvs[tuv_idx[2]];
+            let (u3, v3) = self.uvs[tuv_idx[0]];
+            info.u = Some(a * u1 + b * u2 + (1.0 - a - b) * u3);
+            info.v = Some(a * v1 + b * v2 + (1.0 - a - b) * v3);
+        }
+        Some(info)
     }
 }
 
 impl AABB {
+    /// Returns the distance `t` at which the ray enters the AABB if the ray intersects it in the interval `[0, t_max]`
+    /// or `None` otherwise. If the origin of the ray is inside the AABB `0.0` is returned.
+    /// The implementation follows the slab method by Kay and Kajiya.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` the ray for which to check the intersection
+    /// * `t_max` maximum distance along the ray to consider
+    pub fn intersect_distance(&self, ray: &Ray, t_max: f64) -> Option<f64> {
+        let mut t_near = 0.0;
+        let mut t_far = t_max;
+        for i in 0..3 {
+            let inv_direction = 1.0 / ray.direction[i];
+            let mut t0 = (self.min[i] - ray.origin[i]) * inv_direction;
+            let mut t1 = (self.max[i] - ray.origin[i]) * inv_direction;
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // comparisons are written so NaNs (origin on a slab with a parallel ray) keep the current bounds
+            if t0 > t_near {
+                t_near = t0;
+            }
+            if t1 < t_far {
+                t_far = t1;
+            }
+            if t_near > t_far {
+                return None;
+            }
+        }
+        Some(t_near)
+    }
+
     /// Checks if the ray intersects the AABB and returns `true` if the ray intersects or false if it doesn't.
     /// The implementation is derived from Andrew Woo's: Fast Ray-Box Intersection implemented in C.
     fn intersect(&self, ray: &Ray) -> bool {
@@ -234,19 +392,15 @@ impl AABB {
             }
         }
 
-        let mut coords = [0.0; 3];
         for i in 0..3 {
             if which_plane != i {
-                coords[i] = ray.origin[i] + max_t[which_plane] * ray.direction[i];
-                if coords[i] < self.min[i] || coords[i] > self.max[i] {
+                let coord = ray.origin[i] + max_t[which_plane] * ray.direction[i];
+                if coord < self.min[i] || coord > self.max[i] {
                     return false;
                 }
             }
-            // else {
-            //     coords[i] = candidate_plane[i];
-            // }
         }
-        return true;
+        true
     }
 }
 
@@ -285,6 +439,19 @@ mod test {
         assert!(aabb.intersect(&ray));
     }
 
+    #[test]
+    fn test_aabb_intersection_distance() {
+        let ray = Ray::new(Vector3::new(0.0, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0));
+        let aabb = AABB::new(Vector3::new(2.0, 0.0, 0.0), Vector3::new(3.0, 1.0, 1.0));
+        let aabb_inside = AABB::new(Vector3::new(-1.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        let aabb_behind = AABB::new(Vector3::new(-3.0, 0.0, 0.0), Vector3::new(-2.0, 1.0, 1.0));
+
+        assert_eq!(aabb.intersect_distance(&ray, f64::MAX), Some(2.0));
+        assert_eq!(aabb.intersect_distance(&ray, 1.5), None);
+        assert_eq!(aabb_inside.intersect_distance(&ray, f64::MAX), Some(0.0));
+        assert_eq!(aabb_behind.intersect_distance(&ray, f64::MAX), None);
+    }
+
     #[test]
     fn test_mesh_intersection() {
         let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
@@ -305,11 +472,89 @@ mod test {
         if let Material::Emissive(em) = mat {
             assert_eq!(em.color.r, 1.0);
         } else {
-            assert!(false, "material not set correctly")
+            panic!("material not set correctly")
         }
         assert_eq!(intersection.t, 5.0);
     }
 
+    /// Creates a tessellated unit sphere mesh around the origin
+    fn create_sphere_mesh(rings: usize, segments: usize) -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.materials
+            .push(Material::Emissive(EmissiveMaterial::new(Color::new(
+                1.0, 0.0, 0.0,
+            ))));
+        for i in 0..=rings {
+            let theta = std::f64::consts::PI * i as f64 / rings as f64;
+            for j in 0..segments {
+                let phi = 2.0 * std::f64::consts::PI * j as f64 / segments as f64;
+                mesh.vertex_positions.push(Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                ));
+            }
+        }
+        for i in 0..rings {
+            for j in 0..segments {
+                let a = i * segments + j;
+                let b = i * segments + (j + 1) % segments;
+                let c = a + segments;
+                let d = b + segments;
+                mesh.triangles.push(Triangle::new([a, b, c], 0));
+                mesh.triangles.push(Triangle::new([b, d, c], 0));
+            }
+        }
+        mesh
+    }
+
+    #[test]
+    fn test_mesh_bvh_intersection() {
+        let brute_force = create_sphere_mesh(24, 32);
+        let mut mesh = brute_force.clone();
+        mesh.compute_aabb();
+        mesh.build_bvh();
+
+        for i in 0..25 {
+            for j in 0..25 {
+                let origin = Vector3::new(-3.0, 1.5 - i as f64 * 0.12, 1.5 - j as f64 * 0.12);
+                let ray = Ray::new(origin, Vector3::new(1.0, 0.1, 0.05));
+
+                let expected = brute_force.intersect(&ray);
+                let actual = mesh.intersect(&ray);
+
+                assert_eq!(expected.is_some(), actual.is_some());
+                if let (Some(expected), Some(actual)) = (expected, actual) {
+                    assert_eq!(expected.t, actual.t);
+                    assert_eq!(expected.point, actual.point);
+                    assert_eq!(expected.normal, actual.normal);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mesh_bvh_intersection_inside() {
+        let brute_force = create_sphere_mesh(16, 16);
+        let mut mesh = brute_force.clone();
+        mesh.compute_aabb();
+        mesh.build_bvh();
+
+        for i in 0..40 {
+            let angle = i as f64 * 0.157;
+            let ray = Ray::new(
+                Vector3::new(0.1, -0.2, 0.05),
+                Vector3::new(angle.cos(), angle.sin() * 0.5, angle.sin()),
+            );
+
+            let expected = brute_force.intersect(&ray).map(|i| i.t);
+            let actual = mesh.intersect(&ray).map(|i| i.t);
+
+            assert!(actual.is_some());
+            assert_eq!(expected, actual);
+        }
+    }
+
     #[test]
     fn test_mesh_intersection_negative() {
         let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
@@ -334,6 +579,7 @@ mod test {
             center: Vector3::new(2.0, 0.0, 1.0),
             radius: 1.0,
             material: mat.clone(),
+            motion: None,
         };
 
         let intersection = sphere.intersect(&ray);
@@ -344,7 +590,7 @@ mod test {
         if let Material::Emissive(em) = mat {
             assert_eq!(em.color.r, 1.0);
         } else {
-            assert!(false, "material not set correctly")
+            panic!("material not set correctly")
         }
         assert_eq!(intersection.t, 2.0);
     }
@@ -356,12 +602,59 @@ mod test {
             center: Vector3::new(0.0, 1.0, 0.0),
             radius: 0.5,
             material: Material::Emissive(EmissiveMaterial::new(Color::new(1.0, 0.0, 0.0))),
+            motion: None,
         };
 
         let intersection = sphere.intersect(&ray);
         assert!(intersection.is_none());
     }
 
+    #[test]
+    fn test_moving_sphere_intersection() {
+        let sphere = Sphere {
+            center: Vector3::new(4.0, -2.0, 0.0),
+            radius: 1.0,
+            material: Material::Emissive(EmissiveMaterial::new(Color::new(1.0, 0.0, 0.0))),
+            motion: Some(Vector3::new(0.0, 4.0, 0.0)),
+        };
+        let direction = Vector3::new(1.0, 0.0, 0.0);
+
+        let start = Ray::with_time(Vector3::new(0.0, 0.0, 0.0), direction, 0.0);
+        assert!(sphere.intersect(&start).is_none());
+
+        let middle = Ray::with_time(Vector3::new(0.0, 0.0, 0.0), direction, 0.5);
+        let intersection = sphere.intersect(&middle).unwrap();
+        assert_eq!(intersection.point, Vector3::new(3.0, 0.0, 0.0));
+        assert_eq!(intersection.normal, Vector3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_moving_mesh_intersection() {
+        let mut mesh = Mesh::new();
+        mesh.vertex_positions.push(Vector3::new(5.0, -1.0, -1.0));
+        mesh.vertex_positions.push(Vector3::new(5.0, 1.0, 0.0));
+        mesh.vertex_positions.push(Vector3::new(5.0, -1.0, 1.0));
+        mesh.materials
+            .push(Material::Emissive(EmissiveMaterial::new(Color::new(
+                1.0, 0.0, 0.0,
+            ))));
+        mesh.triangles.push(Triangle::new([0, 1, 2], 0));
+        mesh.motion = Some(Vector3::new(2.0, 0.0, 0.0));
+        mesh.compute_aabb();
+        mesh.build_bvh();
+
+        let ray = Ray::with_time(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            0.5,
+        );
+        let intersection = mesh.intersect(&ray).unwrap();
+        assert_eq!(intersection.point, Vector3::new(6.0, 0.0, 0.0));
+        assert_eq!(intersection.t, 6.0);
+        assert!(mesh.is_occluded(&ray, 6.5));
+        assert!(!mesh.is_occluded(&ray, 5.5));
+    }
+
     #[test]
     fn test_plane_intersection() {
         let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
@@ -380,7 +673,7 @@ mod test {
         if let Material::Emissive(em) = mat {
             assert_eq!(em.color.r, 1.0);
         } else {
-            assert!(false, "material not set correctly")
+            panic!("material not set correctly")
         }
         assert_eq!(intersection.t, 2.0);
     }
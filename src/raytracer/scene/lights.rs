@@ -0,0 +1,909 @@
+use serde::Deserialize;
+
+use std::path::Path;
+
+use crate::{
+    math::{Distribution2D, Vector3},
+    raytracer::{
+        image::{self, Color},
+        raytrace::Ray,
+    },
+};
+
+use super::{
+    intersections::IntersectionInfo,
+    materials::Material,
+    mesh::Mesh,
+    scene::{Object, Plane, Sphere},
+};
+
+/// Light of the scene which can be sampled explicitly for direct lighting.
+/// Point, spot and directional lights are configured in the `lights` section of the scene and can't be seen
+/// by rays, while area lights are created for all emissive objects and the environment map when the scene is precomputed.
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+    Directional(DirectionalLight),
+    #[serde(skip_deserializing)]
+    Area(AreaLight),
+    #[serde(skip_deserializing)]
+    Environment(EnvironmentLight),
+}
+
+impl Light {
+    /// Samples a direction from `point` towards the light.
+    /// Returns `None` if the light doesn't illuminate the point.
+    ///
+    /// # Arguments
+    ///
+    /// * `objects` objects of the scene (needed for area lights)
+    /// * `point` the point to sample the light from
+    /// * `time` point in time at which moving area lights are sampled
+    /// * `u` uniform sample used to choose a direction towards lights with an extent
+    pub fn sample(
+        &self,
+        objects: &[Object],
+        point: &Vector3,
+        time: f64,
+        u: (f64, f64),
+    ) -> Option<LightSample> {
+        match self {
+            Light::Point(l) => l.sample(point),
+            Light::Spot(l) => l.sample(point),
+            Light::Directional(l) => l.sample(),
+            Light::Area(l) => l.sample(&objects[l.object_idx], point, time, u),
+            Light::Environment(l) => l.sample(u),
+        }
+    }
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+fn default_falloff() -> f64 {
+    1.0
+}
+
+/// Light emitting uniformly in all directions from a single point
+#[derive(Deserialize)]
+pub struct PointLight {
+    pub position: Vector3,
+    pub color: Color,
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+}
+
+impl PointLight {
+    fn sample(&self, point: &Vector3) -> Option<LightSample> {
+        let (direction, distance) = direction_to(point, &self.position)?;
+        Some(LightSample::delta(
+            direction,
+            distance,
+            self.color * (self.intensity / (distance * distance)),
+        ))
+    }
+}
+
+/// Point light emitting only in a cone around its direction.
+/// The intensity is constant up to the `inner_angle` and falls off towards the `outer_angle` (both in degrees
+/// measured from the cone's axis). `falloff` is used as an exponent on the transition between both angles.
+#[derive(Deserialize)]
+pub struct SpotLight {
+    pub position: Vector3,
+    pub direction: Vector3,
+    pub color: Color,
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+    #[serde(default)]
+    pub inner_angle: f64,
+    pub outer_angle: f64,
+    #[serde(default = "default_falloff")]
+    pub falloff: f64,
+}
+
+impl SpotLight {
+    fn sample(&self, point: &Vector3) -> Option<LightSample> {
+        let (direction, distance) = direction_to(point, &self.position)?;
+        let attenuation = self.attenuation(&-direction);
+        if attenuation == 0.0 {
+            return None;
+        }
+        Some(LightSample::delta(
+            direction,
+            distance,
+            self.color * (self.intensity * attenuation / (distance * distance)),
+        ))
+    }
+
+    /// Returns the factor in `[0, 1]` by which the intensity is scaled for light leaving in `direction`
+    fn attenuation(&self, direction: &Vector3) -> f64 {
+        let cos_theta = direction.dot(&self.direction.normalized());
+        let cos_outer = self.outer_angle.to_radians().cos();
+        let cos_inner = self.inner_angle.min(self.outer_angle).to_radians().cos();
+        if cos_theta < cos_outer {
+            return 0.0;
+        }
+        if cos_theta >= cos_inner {
+            return 1.0;
+        }
+        ((cos_theta - cos_outer) / (cos_inner - cos_outer)).powf(self.falloff)
+    }
+}
+
+/// Light arriving from a single direction from infinitely far away (e.g. the sun).
+/// `direction` is the direction in which the light travels.
+#[derive(Deserialize)]
+pub struct DirectionalLight {
+    pub direction: Vector3,
+    pub color: Color,
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+}
+
+impl DirectionalLight {
+    fn sample(&self) -> Option<LightSample> {
+        Some(LightSample::delta(
+            -self.direction.normalized(),
+            f64::INFINITY,
+            self.color * self.intensity,
+        ))
+    }
+}
+
+/// Returns the normalized direction and distance from `point` to `target` or `None` if both coincide
+fn direction_to(point: &Vector3, target: &Vector3) -> Option<(Vector3, f64)> {
+    let to_target = *target - *point;
+    let distance = to_target.len();
+    if distance < 1e-9 {
+        return None;
+    }
+    Some((to_target / distance, distance))
+}
+
+impl LightSample {
+    /// Creates a sample of a light described by a delta distribution
+    fn delta(direction: Vector3, distance: f64, emitted: Color) -> LightSample {
+        LightSample {
+            direction,
+            distance,
+            emitted,
+            pdf: 1.0,
+            is_delta: true,
+        }
+    }
+}
+
+/// Emissive object of the scene which can be sampled explicitly for direct lighting.
+/// Area lights reference their object by index and sample it depending on its shape:
+/// - spheres are sampled uniformly in the cone of directions they subtend, or uniformly by area if the point is inside
+/// - meshes are sampled uniformly by area on their emissive triangles, choosing triangles proportional to their area
+/// - infinite planes are sampled by cosine weighted directions of the hemisphere facing the plane
+pub struct AreaLight {
+    /// Index of the emissive object in the object list of the scene
+    pub object_idx: usize,
+    /// Total emissive surface area of the object (infinite for planes)
+    pub area: f64,
+    /// Indices of the emissive triangles of a mesh light
+    triangles: Vec<usize>,
+    /// Cumulative distribution of the areas of the emissive triangles
+    triangle_cdf: Vec<f64>,
+}
+
+/// Result of sampling a light from a point in the scene
+pub struct LightSample {
+    /// Normalized direction from the point towards the sampled point on the light
+    pub direction: Vector3,
+    /// Distance between the point and the sampled point on the light
+    pub distance: f64,
+    /// Color emitted by the light at the sampled point
+    pub emitted: Color,
+    /// Solid angle pdf of the sampled direction
+    pub pdf: f64,
+    /// `true` if the light is described by a delta distribution (point, spot and directional lights)
+    /// and can therefore never be hit by scattered rays
+    pub is_delta: bool,
+}
+
+impl AreaLight {
+    /// Creates a light for an emissive object of the scene
+    ///
+    /// # Arguments
+    ///
+    /// * `object_idx` index of the object in the object list of the scene
+    /// * `o` the emissive object
+    pub fn from_object(object_idx: usize, o: &Object) -> AreaLight {
+        let mut light = AreaLight {
+            object_idx,
+            area: 0.0,
+            triangles: Vec::new(),
+            triangle_cdf: Vec::new(),
+        };
+        match o {
+            Object::Plane(_) => light.area = f64::INFINITY,
+            Object::Sphere(s) => light.area = 4.0 * std::f64::consts::PI * s.radius * s.radius,
+            Object::Mesh(m) => {
+                for (idx, triangle) in m.triangles.iter().enumerate() {
+                    if let Material::Emissive(_) = &m.materials[triangle.material_idx] {
+                        light.area += triangle_area(m, idx);
+                        light.triangles.push(idx);
+                        light.triangle_cdf.push(light.area);
+                    }
+                }
+            }
+        }
+        light
+    }
+
+    /// Samples a direction from `point` towards the light.
+    /// Returns `None` if the light can't be seen from the point (e.g. the point lies on the light's surface).
+    ///
+    /// # Arguments
+    ///
+    /// * `o` the object of the light
+    /// * `point` the point to sample the light from
+    /// * `time` point in time at which the object is sampled if it moves
+    /// * `u` uniform sample used to choose the direction or point on the light
+    pub fn sample(
+        &self,
+        o: &Object,
+        point: &Vector3,
+        time: f64,
+        u: (f64, f64),
+    ) -> Option<LightSample> {
+        let (direction, distance, emitted, pdf) = match o {
+            Object::Plane(plane) => self.sample_plane(plane, point, u)?,
+            Object::Sphere(sphere) => self.sample_sphere(sphere, point, time, u)?,
+            Object::Mesh(mesh) => {
+                let (light_point, normal, emitted) = self.sample_triangles(mesh, u);
+                let light_point = light_point + mesh.offset_at(time);
+                let (direction, distance, pdf) =
+                    self.area_to_solid_angle(point, &light_point, &normal)?;
+                (direction, distance, emitted, pdf)
+            }
+        };
+        if pdf <= 0.0 || !pdf.is_finite() {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            emitted,
+            pdf,
+            is_delta: false,
+        })
+    }
+
+    /// Returns the solid angle pdf with which [sample](Self::sample) would have chosen the direction of `ray`
+    /// towards the intersection point on the light.
+    ///
+    /// # Arguments
+    ///
+    /// * `o` the object of the light
+    /// * `ray` ray which hit the light
+    /// * `intersection` intersection of the ray with the light
+    pub fn pdf(&self, o: &Object, ray: &Ray, intersection: &IntersectionInfo) -> f64 {
+        match o {
+            Object::Plane(plane) => {
+                f64::abs(plane.normal.normalized().dot(&ray.direction))
+                    * std::f64::consts::FRAC_1_PI
+            }
+            Object::Sphere(sphere) => match sphere_cone(sphere, &ray.origin, ray.time) {
+                Some((_, cos_theta_max)) => uniform_cone_pdf(cos_theta_max),
+                None => self.area_pdf(ray, intersection),
+            },
+            Object::Mesh(_) => self.area_pdf(ray, intersection),
+        }
+    }
+
+    /// Converts the area pdf of a uniformly sampled point on the light's surface into the solid angle pdf
+    /// of the direction of `ray` towards it
+    fn area_pdf(&self, ray: &Ray, intersection: &IntersectionInfo) -> f64 {
+        let cos_light = f64::abs(intersection.normal.dot(&ray.direction));
+        if cos_light == 0.0 {
+            return 0.0;
+        }
+        intersection.t * intersection.t / (cos_light * self.area)
+    }
+
+    /// Samples a direction towards a sphere. Points outside of the sphere sample the cone of directions
+    /// subtended by the sphere uniformly, which never generates directions that miss the sphere or hit its back side.
+    /// Points inside the sphere fall back to uniform area sampling.
+    fn sample_sphere(
+        &self,
+        sphere: &Sphere,
+        point: &Vector3,
+        time: f64,
+        u: (f64, f64),
+    ) -> Option<(Vector3, f64, Color, f64)> {
+        let emitted = sphere.material.emitted();
+        let center = sphere.center_at(time);
+        let (axis, cos_theta_max) = match sphere_cone(sphere, point, time) {
+            Some(cone) => cone,
+            None => {
+                let normal = Vector3::sample_unit_vector(u);
+                let light_point = center + normal * sphere.radius;
+                let (direction, distance, pdf) =
+                    self.area_to_solid_angle(point, &light_point, &normal)?;
+                return Some((direction, distance, emitted, pdf));
+            }
+        };
+
+        let cos_theta = 1.0 - u.0 * (1.0 - cos_theta_max);
+        let sin_theta = f64::sqrt(f64::max(0.0, 1.0 - cos_theta * cos_theta));
+        let phi = 2.0 * std::f64::consts::PI * u.1;
+        let (tangent, bitangent) = axis.orthonormal_basis();
+        let direction = (tangent * (sin_theta * phi.cos())
+            + bitangent * (sin_theta * phi.sin())
+            + axis * cos_theta)
+            .normalized();
+
+        // closest intersection of the sampled direction with the sphere
+        let center_distance = (center - *point).len();
+        let projected = center_distance * cos_theta;
+        let discriminant = sphere.radius * sphere.radius
+            - center_distance * center_distance * sin_theta * sin_theta;
+        let distance = projected - f64::sqrt(f64::max(0.0, discriminant));
+
+        Some((
+            direction,
+            distance,
+            emitted,
+            uniform_cone_pdf(cos_theta_max),
+        ))
+    }
+
+    /// Samples a direction towards an infinite plane by cosine weighted sampling of the hemisphere facing the plane
+    fn sample_plane(
+        &self,
+        plane: &Plane,
+        point: &Vector3,
+        u: (f64, f64),
+    ) -> Option<(Vector3, f64, Color, f64)> {
+        let mut normal = plane.normal.normalized();
+        let offset = (plane.center - *point).dot(&normal);
+        if offset.abs() < 1e-9 {
+            return None;
+        }
+        if offset < 0.0 {
+            normal = -normal;
+        }
+        let mut direction = normal + Vector3::sample_unit_vector(u);
+        if direction.near_zero() {
+            direction = normal;
+        }
+        let direction = direction.normalized();
+        let cos_theta = direction.dot(&normal);
+        if cos_theta <= 0.0 {
+            return None;
+        }
+        let distance = offset.abs() / cos_theta;
+        Some((
+            direction,
+            distance,
+            plane.material.emitted(),
+            cos_theta * std::f64::consts::FRAC_1_PI,
+        ))
+    }
+
+    /// Samples a point uniformly by area on the emissive triangles of the mesh.
+    /// Returns the point, the normal of the triangle and the color emitted by its material.
+    /// The first dimension of `u` chooses the triangle and is rescaled afterwards to be reused for the point on it.
+    fn sample_triangles(&self, mesh: &Mesh, u: (f64, f64)) -> (Vector3, Vector3, Color) {
+        let target = u.0 * self.area;
+        let idx = self
+            .triangle_cdf
+            .partition_point(|&cdf| cdf < target)
+            .min(self.triangles.len() - 1);
+        let triangle = &mesh.triangles[self.triangles[idx]];
+        let cdf_start = if idx == 0 {
+            0.0
+        } else {
+            self.triangle_cdf[idx - 1]
+        };
+        let u_point = ((target - cdf_start) / (self.triangle_cdf[idx] - cdf_start)).clamp(0.0, 1.0);
+
+        let a = mesh.vertex_positions[triangle.vertex_idx[0]];
+        let b = mesh.vertex_positions[triangle.vertex_idx[1]];
+        let c = mesh.vertex_positions[triangle.vertex_idx[2]];
+        let s = u_point.sqrt();
+        let r = u.1;
+        let light_point = a * (1.0 - s) + b * (s * (1.0 - r)) + c * (s * r);
+        let normal = (b - a).cross(&(c - a)).normalized();
+        (
+            light_point,
+            normal,
+            mesh.materials[triangle.material_idx].emitted(),
+        )
+    }
+
+    /// Converts a point sampled uniformly by area on the light's surface into a direction and distance
+    /// from `point` and the corresponding solid angle pdf
+    fn area_to_solid_angle(
+        &self,
+        point: &Vector3,
+        light_point: &Vector3,
+        light_normal: &Vector3,
+    ) -> Option<(Vector3, f64, f64)> {
+        let to_light = *light_point - *point;
+        let distance = to_light.len();
+        if distance < 1e-9 {
+            return None;
+        }
+        let direction = to_light / distance;
+        let cos_light = f64::abs(light_normal.dot(&direction));
+        if cos_light == 0.0 {
+            return None;
+        }
+        Some((
+            direction,
+            distance,
+            distance * distance / (cos_light * self.area),
+        ))
+    }
+}
+
+/// Configuration of an environment map surrounding the scene.
+/// `path` references an equirectangular Radiance `.hdr` image, `rotation` rotates it around the y-axis (in degrees)
+/// and `intensity` scales its radiance.
+#[derive(Deserialize)]
+pub struct EnvironmentConfig {
+    pub path: String,
+    #[serde(default)]
+    pub rotation: f64,
+    #[serde(default = "default_intensity")]
+    pub intensity: f64,
+}
+
+/// Equirectangular environment map which is seen by rays leaving the scene.
+/// Directions are sampled proportional to the luminance of the map (weighted by the solid angle of its pixels)
+/// so bright regions like the sun are found by direct lighting.
+pub struct EnvironmentLight {
+    pixels: Vec<Color>,
+    width: usize,
+    height: usize,
+    /// Rotation around the y-axis as a fraction of a full turn
+    rotation: f64,
+    intensity: f64,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentLight {
+    /// Loads the environment map of the configuration
+    ///
+    /// # Panics
+    ///
+    /// If the image can't be read
+    pub fn new(config: &EnvironmentConfig) -> EnvironmentLight {
+        let (pixels, width, height) = image::read_hdr_image(Path::new(&config.path));
+        EnvironmentLight::from_pixels(pixels, width, height, config.rotation, config.intensity)
+    }
+
+    /// Creates an environment map from linear pixel colors in row -> column order.
+    /// The first row is mapped to the direction `+y`, the center column to `-z`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pixels` pixel colors (size: width * height)
+    /// * `width` width of the image
+    /// * `height` height of the image
+    /// * `rotation` rotation around the y-axis in degrees
+    /// * `intensity` factor by which the radiance is scaled
+    pub fn from_pixels(
+        pixels: Vec<Color>,
+        width: usize,
+        height: usize,
+        rotation: f64,
+        intensity: f64,
+    ) -> EnvironmentLight {
+        let mut weights = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let sin_theta = f64::sin(std::f64::consts::PI * (y as f64 + 0.5) / height as f64);
+            for x in 0..width {
+                weights.push(pixels[y * width + x].luminance().max(0.0) * sin_theta);
+            }
+        }
+        EnvironmentLight {
+            distribution: Distribution2D::new(&weights, width, height),
+            pixels,
+            width,
+            height,
+            rotation: rotation / 360.0,
+            intensity,
+        }
+    }
+
+    /// Returns the radiance arriving from `direction`
+    pub fn radiance(&self, direction: &Vector3) -> Color {
+        let (u, v) = self.direction_to_uv(direction);
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[y * self.width + x] * self.intensity
+    }
+
+    /// Samples a direction proportional to the luminance of the environment
+    ///
+    /// # Arguments
+    ///
+    /// * `u` uniform sample mapped to the environment map
+    pub fn sample(&self, u: (f64, f64)) -> Option<LightSample> {
+        let (uv, uv_pdf) = self.distribution.sample_continuous(u);
+        let direction = self.uv_to_direction(uv);
+        let pdf = self.uv_pdf_to_solid_angle(uv_pdf, uv.1);
+        if pdf <= 0.0 || !pdf.is_finite() {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance: f64::INFINITY,
+            emitted: self.radiance(&direction),
+            pdf,
+            is_delta: false,
+        })
+    }
+
+    /// Returns the solid angle pdf with which [sample](Self::sample) would have chosen `direction`
+    pub fn pdf(&self, direction: &Vector3) -> f64 {
+        let uv = self.direction_to_uv(direction);
+        self.uv_pdf_to_solid_angle(self.distribution.pdf(uv), uv.1)
+    }
+
+    /// Converts a pdf with respect to the image coordinates into a solid angle pdf
+    fn uv_pdf_to_solid_angle(&self, uv_pdf: f64, v: f64) -> f64 {
+        let sin_theta = f64::sin(std::f64::consts::PI * v);
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        uv_pdf / (2.0 * std::f64::consts::PI * std::f64::consts::PI * sin_theta)
+    }
+
+    fn direction_to_uv(&self, direction: &Vector3) -> (f64, f64) {
+        let direction = direction.normalized();
+        let phi = f64::atan2(direction.x(), -direction.z());
+        let u = (0.5 + phi / (2.0 * std::f64::consts::PI) - self.rotation).rem_euclid(1.0);
+        let v = f64::acos(direction.y().clamp(-1.0, 1.0)) / std::f64::consts::PI;
+        (u, v)
+    }
+
+    fn uv_to_direction(&self, (u, v): (f64, f64)) -> Vector3 {
+        let phi = (u - 0.5 + self.rotation) * 2.0 * std::f64::consts::PI;
+        let theta = v * std::f64::consts::PI;
+        Vector3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        )
+    }
+}
+
+/// Returns the normalized direction towards the center of the sphere and the cosine of the half angle
+/// of the cone subtended by the sphere at the given point in time as seen from `point`,
+/// or `None` if the point is inside the sphere.
+fn sphere_cone(sphere: &Sphere, point: &Vector3, time: f64) -> Option<(Vector3, f64)> {
+    let to_center = sphere.center_at(time) - *point;
+    let sqr_distance = to_center.sqr_len();
+    let sqr_radius = sphere.radius * sphere.radius;
+    if sqr_distance <= sqr_radius {
+        return None;
+    }
+    let sin_theta_max_sqr = sqr_radius / sqr_distance;
+    let cos_theta_max = f64::sqrt(f64::max(0.0, 1.0 - sin_theta_max_sqr));
+    Some((to_center / sqr_distance.sqrt(), cos_theta_max))
+}
+
+/// Returns the solid angle pdf of directions sampled uniformly in a cone with the given half angle
+fn uniform_cone_pdf(cos_theta_max: f64) -> f64 {
+    1.0 / (2.0 * std::f64::consts::PI * (1.0 - cos_theta_max))
+}
+
+/// Computes the area of a single triangle of the mesh
+fn triangle_area(mesh: &Mesh, triangle_idx: usize) -> f64 {
+    let idx = mesh.triangles[triangle_idx].vertex_idx;
+    let a = mesh.vertex_positions[idx[0]];
+    let b = mesh.vertex_positions[idx[1]];
+    let c = mesh.vertex_positions[idx[2]];
+    (b - a).cross(&(c - a)).len() * 0.5
+}
+
+/// Combines the pdfs of two sampling strategies by the power heuristic (with exponent 2) and returns the
+/// multiple importance sampling weight for a sample generated by the strategy with pdf `pdf_a`.
+///
+/// # Arguments
+///
+/// * `pdf_a` pdf of the strategy which generated the sample
+/// * `pdf_b` pdf of the other strategy for the same sample
+pub fn power_heuristic(pdf_a: f64, pdf_b: f64) -> f64 {
+    let a = pdf_a * pdf_a;
+    let b = pdf_b * pdf_b;
+    if a + b == 0.0 {
+        return 0.0;
+    }
+    a / (a + b)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        math::Vector3,
+        raytracer::{
+            image::Color,
+            raytrace::Ray,
+            sampler::{hash, to_unit},
+            scene::{
+                intersections::Intersectable,
+                materials::{EmissiveMaterial, LambertianMaterial, Material},
+                mesh::{Mesh, Triangle},
+                Object, Plane, Sphere,
+            },
+        },
+    };
+
+    use super::{
+        power_heuristic, AreaLight, DirectionalLight, EnvironmentLight, Light, PointLight,
+        SpotLight,
+    };
+
+    /// Returns a uniform sample of the unit square which is fixed for each `index`
+    fn random_sample(index: u64) -> (f64, f64) {
+        (to_unit(hash(&[index, 0])), to_unit(hash(&[index, 1])))
+    }
+
+    /// Checks that the pdfs of sampled directions match the pdfs evaluated for rays hitting the light
+    fn assert_consistent_pdfs(o: &Object, point: &Vector3) {
+        let light = AreaLight::from_object(0, o);
+        for index in 0..200 {
+            let sample = light.sample(o, point, 0.0, random_sample(index)).unwrap();
+            let ray = Ray::new(*point, sample.direction);
+            let intersection = o.intersect(&ray).unwrap();
+            let pdf = light.pdf(o, &ray, &intersection);
+
+            assert!(
+                (intersection.t - sample.distance).abs() < 1e-6 * sample.distance,
+                "sampled distance {} does not match intersection {}",
+                sample.distance,
+                intersection.t
+            );
+            assert!(
+                (pdf - sample.pdf).abs() < 1e-6 * sample.pdf,
+                "sampled pdf {} does not match evaluated pdf {}",
+                sample.pdf,
+                pdf
+            );
+        }
+    }
+
+    fn emissive() -> Material {
+        Material::Emissive(EmissiveMaterial::new(Color::new(1.0, 1.0, 1.0)))
+    }
+
+    #[test]
+    fn test_sphere_light_pdf() {
+        let sphere = Object::Sphere(Sphere {
+            center: Vector3::new(1.0, 4.0, -2.0),
+            radius: 1.5,
+            material: emissive(),
+            motion: None,
+        });
+
+        assert_consistent_pdfs(&sphere, &Vector3::new(0.0, 0.0, 0.0));
+        assert_consistent_pdfs(&sphere, &Vector3::new(1.2, 3.5, -2.1));
+    }
+
+    #[test]
+    fn test_sphere_light_solid_angle() {
+        let sphere = Object::Sphere(Sphere {
+            center: Vector3::new(0.0, 0.0, 4.0),
+            radius: 2.0,
+            material: emissive(),
+            motion: None,
+        });
+        let light = AreaLight::from_object(0, &sphere);
+        let sample = light
+            .sample(&sphere, &Vector3::new(0.0, 0.0, 0.0), 0.0, random_sample(0))
+            .unwrap();
+
+        // sin(theta_max) = 0.5 so the sphere subtends a cone with half angle 30 degrees
+        let solid_angle = 2.0 * std::f64::consts::PI * (1.0 - f64::sqrt(0.75));
+        assert!((1.0 / sample.pdf - solid_angle).abs() < 1e-9);
+        assert!(sample.direction.z() >= f64::sqrt(0.75) - 1e-9);
+    }
+
+    #[test]
+    fn test_mesh_light_pdf() {
+        let mut mesh = Mesh::new();
+        mesh.materials.push(emissive());
+        mesh.materials
+            .push(Material::Lambertian(LambertianMaterial::new(Color::new(
+                1.0, 1.0, 1.0,
+            ))));
+        mesh.vertex_positions.push(Vector3::new(-1.0, 3.0, -1.0));
+        mesh.vertex_positions.push(Vector3::new(2.0, 3.0, -1.0));
+        mesh.vertex_positions.push(Vector3::new(-1.0, 3.0, 1.0));
+        mesh.vertex_positions.push(Vector3::new(2.0, 3.0, 1.0));
+        mesh.vertex_positions.push(Vector3::new(5.0, 3.0, 1.0));
+        mesh.triangles.push(Triangle::new([0, 1, 2], 0));
+        mesh.triangles.push(Triangle::new([1, 3, 2], 0));
+        mesh.triangles.push(Triangle::new([1, 4, 3], 1));
+        let mesh = Object::Mesh(mesh);
+
+        let light = AreaLight::from_object(0, &mesh);
+        assert!((light.area - 6.0).abs() < 1e-9);
+        assert_consistent_pdfs(&mesh, &Vector3::new(0.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_plane_light_pdf() {
+        let plane = Object::Plane(Plane {
+            center: Vector3::new(0.0, 10.0, 0.0),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+            material: emissive(),
+        });
+
+        assert_consistent_pdfs(&plane, &Vector3::new(0.0, 0.0, 0.0));
+        assert_consistent_pdfs(&plane, &Vector3::new(3.0, 12.0, 1.0));
+    }
+
+    #[test]
+    fn test_point_light_sample() {
+        let light = Light::Point(PointLight {
+            position: Vector3::new(0.0, 2.0, 0.0),
+            color: Color::new(1.0, 0.5, 0.25),
+            intensity: 8.0,
+        });
+        let sample = light
+            .sample(&[], &Vector3::new(0.0, 0.0, 0.0), 0.0, (0.5, 0.5))
+            .unwrap();
+
+        assert!(sample.is_delta);
+        assert_eq!(sample.pdf, 1.0);
+        assert_eq!(sample.distance, 2.0);
+        assert_eq!(sample.direction, Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.emitted, Color::new(2.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn test_spot_light_sample() {
+        let light = Light::Spot(SpotLight {
+            position: Vector3::new(0.0, 1.0, 0.0),
+            direction: Vector3::new(0.0, -1.0, 0.0),
+            color: Color::new(1.0, 1.0, 1.0),
+            intensity: 1.0,
+            inner_angle: 30.0,
+            outer_angle: 60.0,
+            falloff: 1.0,
+        });
+
+        let inside = light
+            .sample(&[], &Vector3::new(0.0, 0.0, 0.0), 0.0, (0.5, 0.5))
+            .unwrap();
+        assert_eq!(inside.emitted, Color::new(1.0, 1.0, 1.0));
+
+        // 45 degrees from the axis lies halfway between the cosines of both angles
+        let falloff = light
+            .sample(&[], &Vector3::new(1.0, 0.0, 0.0), 0.0, (0.5, 0.5))
+            .unwrap();
+        let cos_45 = f64::sqrt(0.5);
+        let cos_60 = 0.5;
+        let cos_30 = f64::sqrt(0.75);
+        let expected = (cos_45 - cos_60) / (cos_30 - cos_60) / 2.0;
+        assert!((falloff.emitted.r - expected).abs() < 1e-9);
+
+        let outside = light.sample(&[], &Vector3::new(2.0, 0.0, 0.0), 0.0, (0.5, 0.5));
+        assert!(outside.is_none());
+    }
+
+    #[test]
+    fn test_directional_light_sample() {
+        let light = Light::Directional(DirectionalLight {
+            direction: Vector3::new(0.0, -2.0, 0.0),
+            color: Color::new(1.0, 1.0, 1.0),
+            intensity: 3.0,
+        });
+        let sample = light
+            .sample(&[], &Vector3::new(5.0, 0.0, 1.0), 0.0, (0.5, 0.5))
+            .unwrap();
+
+        assert!(sample.is_delta);
+        assert_eq!(sample.distance, f64::INFINITY);
+        assert_eq!(sample.direction, Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.emitted, Color::new(3.0, 3.0, 3.0));
+    }
+
+    #[test]
+    fn test_power_heuristic() {
+        assert_eq!(power_heuristic(1.0, 1.0), 0.5);
+        assert_eq!(power_heuristic(0.0, 0.0), 0.0);
+        assert_eq!(power_heuristic(3.0, 1.0), 0.9);
+    }
+
+    #[test]
+    fn test_sphere_light_sample() {
+        let sphere = Object::Sphere(Sphere {
+            center: Vector3::new(0.0, 5.0, 0.0),
+            radius: 1.0,
+            material: Material::Emissive(EmissiveMaterial::new(Color::new(1.0, 0.5, 0.0))),
+            motion: None,
+        });
+        let light = AreaLight::from_object(0, &sphere);
+        let point = Vector3::new(0.0, 0.0, 0.0);
+
+        for index in 0..100 {
+            let sample = light
+                .sample(&sphere, &point, 0.0, random_sample(index))
+                .unwrap();
+            assert!(sample.pdf > 0.0);
+            assert!(sample.distance >= 4.0 - 1e-9 && sample.distance <= 6.0 + 1e-9);
+            assert!((sample.direction.len() - 1.0).abs() < 1e-9);
+            assert_eq!(sample.emitted, Color::new(1.0, 0.5, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_environment_light_lookup() {
+        let pixels = vec![
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+            Color::new(1.0, 1.0, 1.0),
+        ];
+        let light = EnvironmentLight::from_pixels(pixels.clone(), 4, 1, 0.0, 2.0);
+        // -z looks at the center of the image, +x is a quarter turn further
+        assert_eq!(
+            light.radiance(&Vector3::new(0.0, 0.0, -1.0)),
+            Color::new(0.0, 0.0, 2.0)
+        );
+        assert_eq!(
+            light.radiance(&Vector3::new(1.0, 0.0, 0.0)),
+            Color::new(2.0, 2.0, 2.0)
+        );
+
+        let rotated = EnvironmentLight::from_pixels(pixels, 4, 1, 90.0, 1.0);
+        assert_eq!(
+            rotated.radiance(&Vector3::new(1.0, 0.0, 0.0)),
+            Color::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn test_environment_light_pdf() {
+        let mut pixels = vec![Color::new(0.1, 0.1, 0.1); 16 * 8];
+        pixels[2 * 16 + 5] = Color::new(100.0, 90.0, 80.0);
+        let light = EnvironmentLight::from_pixels(pixels, 16, 8, 30.0, 1.0);
+
+        let mut bright = 0;
+        for index in 0..500 {
+            let sample = light.sample(random_sample(index)).unwrap();
+            let pdf = light.pdf(&sample.direction);
+            assert!((pdf - sample.pdf).abs() < 1e-6 * sample.pdf);
+            assert_eq!(sample.emitted, light.radiance(&sample.direction));
+            if sample.emitted.r == 100.0 {
+                bright += 1;
+            }
+        }
+        assert!(bright > 400);
+    }
+
+    #[test]
+    fn test_environment_light_integral() {
+        // importance sampled estimate of the irradiance integral over the sphere of a constant environment
+        let light = EnvironmentLight::from_pixels(
+            vec![Color::new(1.0, 1.0, 1.0); 32 * 16],
+            32,
+            16,
+            0.0,
+            1.0,
+        );
+        let n = 20000;
+        let estimate: f64 = (0..n)
+            .map(|index| {
+                let sample = light.sample(random_sample(index)).unwrap();
+                sample.emitted.r / sample.pdf
+            })
+            .sum::<f64>()
+            / n as f64;
+        let expected = 4.0 * std::f64::consts::PI;
+        assert!((estimate - expected).abs() < 0.02 * expected);
+    }
+}
@@ -1,9 +1,8 @@
-use rand::Rng;
 use serde::Deserialize;
 
 use crate::{
     math::Vector3,
-    raytracer::{image::Color, raytrace::Ray},
+    raytracer::{image::Color, raytrace::Ray, sampler::Sampler},
 };
 
 use super::intersections::IntersectionInfo;
@@ -11,8 +10,79 @@ use super::intersections::IntersectionInfo;
 /// Trait for all Materials to provide Scattering for raytracing.
 /// Implementing materials can either return a new so called scattered ray that bounces from the intersection point
 /// and / or a color value for the intersection point.
+///
+/// Materials which don't scatter along a delta distribution additionally provide the evaluation of their BSDF
+/// and the pdf of their scattered directions, so directions sampled by other means (e.g. towards lights)
+/// can be weighted correctly.
 pub trait Scatter {
-    fn scatter(&self, ray: &Ray, intersection: &IntersectionInfo) -> Option<(Option<Ray>, Color)>;
+    /// Scatters the ray at the intersection, where all random decisions are taken from the uniform numbers of `sample`
+    fn scatter(
+        &self,
+        ray: &Ray,
+        intersection: &IntersectionInfo,
+        sample: BsdfSample,
+    ) -> Option<(Option<Ray>, Color)>;
+
+    /// Evaluates the BSDF multiplied by the cosine term for light arriving from `direction`
+    /// and leaving along the inverse direction of `ray`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` the incoming ray
+    /// * `intersection` intersection of the ray with the object
+    /// * `direction` normalized direction from the intersection point towards the incident light
+    fn eval(&self, _ray: &Ray, _intersection: &IntersectionInfo, _direction: &Vector3) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// Returns the solid angle pdf with which [scatter](Self::scatter) samples `direction`.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` the incoming ray
+    /// * `intersection` intersection of the ray with the object
+    /// * `direction` normalized direction of the scattered ray
+    fn pdf(&self, _ray: &Ray, _intersection: &IntersectionInfo, _direction: &Vector3) -> f64 {
+        0.0
+    }
+
+    /// Returns `true` if scattered directions follow a delta distribution (perfect mirrors and glass),
+    /// in which case [eval](Self::eval) and [pdf](Self::pdf) are always zero and lights can't be sampled explicitly.
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
+
+/// Uniform random numbers of one scattering event.
+/// Every material receives the same amount of numbers, so the dimensions of a [Sampler] stay consistent along a path.
+#[derive(Clone, Copy, Debug)]
+pub struct BsdfSample {
+    /// sample of the scattered direction
+    pub u: (f64, f64),
+    /// sample of discrete choices like reflection or refraction, or of a third dimension
+    pub u_choice: f64,
+}
+
+impl BsdfSample {
+    /// Takes the numbers of the next scattering event from the sampler
+    pub fn new(sampler: &mut dyn Sampler) -> BsdfSample {
+        BsdfSample {
+            u: sampler.get_2d(),
+            u_choice: sampler.get_1d(),
+        }
+    }
+}
+
+/// Evaluates the cosine weighted lambertian BSDF `albedo / pi * cos(theta)` for the given normal and direction
+#[inline]
+fn lambertian_eval(albedo: Color, normal: &Vector3, direction: &Vector3) -> Color {
+    albedo * lambertian_pdf(normal, direction)
+}
+
+/// Returns the pdf of cosine weighted hemisphere sampling around the normal
+#[inline]
+fn lambertian_pdf(normal: &Vector3, direction: &Vector3) -> f64 {
+    f64::max(normal.dot(direction), 0.0) * std::f64::consts::FRAC_1_PI
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -25,14 +95,59 @@ pub enum Material {
     Emissive(EmissiveMaterial),
 }
 
+impl Material {
+    /// Returns the color emitted by the material which is black for all non-emissive materials
+    pub fn emitted(&self) -> Color {
+        match self {
+            Material::Emissive(e) => e.color,
+            _ => Color::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
 impl Scatter for Material {
-    fn scatter(&self, ray: &Ray, intersection: &IntersectionInfo) -> Option<(Option<Ray>, Color)> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        intersection: &IntersectionInfo,
+        sample: BsdfSample,
+    ) -> Option<(Option<Ray>, Color)> {
+        match self {
+            Material::Lambertian(l) => l.scatter(ray, intersection, sample),
+            Material::Metal(m) => m.scatter(ray, intersection, sample),
+            Material::Dieletrics(d) => d.scatter(ray, intersection, sample),
+            Material::Emissive(l) => l.scatter(ray, intersection, sample),
+            Material::Texture(t) => t.scatter(ray, intersection, sample),
+        }
+    }
+
+    fn eval(&self, ray: &Ray, intersection: &IntersectionInfo, direction: &Vector3) -> Color {
+        match self {
+            Material::Lambertian(l) => l.eval(ray, intersection, direction),
+            Material::Metal(m) => m.eval(ray, intersection, direction),
+            Material::Dieletrics(d) => d.eval(ray, intersection, direction),
+            Material::Emissive(l) => l.eval(ray, intersection, direction),
+            Material::Texture(t) => t.eval(ray, intersection, direction),
+        }
+    }
+
+    fn pdf(&self, ray: &Ray, intersection: &IntersectionInfo, direction: &Vector3) -> f64 {
+        match self {
+            Material::Lambertian(l) => l.pdf(ray, intersection, direction),
+            Material::Metal(m) => m.pdf(ray, intersection, direction),
+            Material::Dieletrics(d) => d.pdf(ray, intersection, direction),
+            Material::Emissive(l) => l.pdf(ray, intersection, direction),
+            Material::Texture(t) => t.pdf(ray, intersection, direction),
+        }
+    }
+
+    fn is_specular(&self) -> bool {
         match self {
-            Material::Lambertian(l) => l.scatter(ray, intersection),
-            Material::Metal(m) => m.scatter(ray, intersection),
-            Material::Dieletrics(d) => d.scatter(ray, intersection),
-            Material::Emissive(l) => l.scatter(ray, intersection),
-            Material::Texture(t) => t.scatter(ray, intersection),
+            Material::Lambertian(l) => l.is_specular(),
+            Material::Metal(m) => m.is_specular(),
+            Material::Dieletrics(d) => d.is_specular(),
+            Material::Emissive(l) => l.is_specular(),
+            Material::Texture(t) => t.is_specular(),
         }
     }
 }
@@ -43,26 +158,44 @@ pub struct LambertianMaterial {
 }
 
 impl LambertianMaterial {
-    #[cfg(test)]
+    /// Creates a diffuse material reflecting the `albedo`
     pub fn new(albedo: Color) -> LambertianMaterial {
         LambertianMaterial { albedo }
     }
 }
 
 impl Scatter for LambertianMaterial {
-    fn scatter(&self, _ray: &Ray, intersection: &IntersectionInfo) -> Option<(Option<Ray>, Color)> {
-        let mut scatter_direction = intersection.normal + Vector3::random_unit_vector();
+    fn scatter(
+        &self,
+        ray: &Ray,
+        intersection: &IntersectionInfo,
+        sample: BsdfSample,
+    ) -> Option<(Option<Ray>, Color)> {
+        let normal = intersection.facing_normal(ray);
+        let mut scatter_direction = normal + Vector3::sample_unit_vector(sample.u);
 
         if scatter_direction.near_zero() {
-            scatter_direction = intersection.normal;
+            scatter_direction = normal;
         }
 
-        let scattered = Ray::new(intersection.point, scatter_direction);
+        let scattered = Ray::with_time(intersection.point, scatter_direction, ray.time);
 
         let attenuation = self.albedo;
 
         Some((Some(scattered), attenuation))
     }
+
+    fn eval(&self, ray: &Ray, intersection: &IntersectionInfo, direction: &Vector3) -> Color {
+        lambertian_eval(self.albedo, &intersection.facing_normal(ray), direction)
+    }
+
+    fn pdf(&self, ray: &Ray, intersection: &IntersectionInfo, direction: &Vector3) -> f64 {
+        lambertian_pdf(&intersection.facing_normal(ray), direction)
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -71,7 +204,7 @@ pub struct EmissiveMaterial {
 }
 
 impl EmissiveMaterial {
-    #[cfg(test)]
+    /// Creates a material emitting the radiance `color`, objects with it become area lights
     pub fn new(color: Color) -> EmissiveMaterial {
         EmissiveMaterial { color }
     }
@@ -82,8 +215,9 @@ impl Scatter for EmissiveMaterial {
         &self,
         _ray: &Ray,
         _intersection: &IntersectionInfo,
+        _sample: BsdfSample,
     ) -> Option<(Option<Ray>, Color)> {
-        return Some((None, self.color));
+        Some((None, self.color))
     }
 }
 
@@ -94,8 +228,13 @@ pub struct DielectricsMaterial {
 }
 
 impl DielectricsMaterial {
-    #[cfg(test)]
-    fn new(tint: Color, refraction_index: f64) -> DielectricsMaterial {
+    /// Creates a glass-like material tinting the reflected and refracted light
+    ///
+    /// # Arguments
+    ///
+    /// * `tint` color by which the reflected and refracted light is multiplied
+    /// * `refraction_index` index of refraction of the material, e.g. `1.5` for glass
+    pub fn new(tint: Color, refraction_index: f64) -> DielectricsMaterial {
         DielectricsMaterial {
             tint,
             refraction_index,
@@ -117,7 +256,12 @@ impl DielectricsMaterial {
 }
 
 impl Scatter for DielectricsMaterial {
-    fn scatter(&self, ray: &Ray, intersection: &IntersectionInfo) -> Option<(Option<Ray>, Color)> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        intersection: &IntersectionInfo,
+        sample: BsdfSample,
+    ) -> Option<(Option<Ray>, Color)> {
         let attenuatin = self.tint;
         let front_face = intersection.normal.dot(&ray.direction) <= 0.0;
         let refraction_ratio = if front_face {
@@ -136,17 +280,15 @@ impl Scatter for DielectricsMaterial {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
 
-        let mut rng = rand::thread_rng();
-
         let mut direction =
             DielectricsMaterial::refract(&unit_direction, &normal, refraction_ratio);
         if cannot_refract
-            || DielectricsMaterial::reflectance(cos_theta, refraction_ratio) > rng.gen::<f64>()
+            || DielectricsMaterial::reflectance(cos_theta, refraction_ratio) > sample.u_choice
         {
             direction = unit_direction.reflect(&normal);
         }
 
-        let scattered = Ray::new(intersection.point, direction);
+        let scattered = Ray::with_time(intersection.point, direction, ray.time);
 
         Some((Some(scattered), attenuatin))
     }
@@ -159,18 +301,24 @@ pub struct MetalMaterial {
 }
 
 impl MetalMaterial {
-    #[cfg(test)]
-    fn new(albedo: Color, fuzziness: f64) -> MetalMaterial {
+    /// Creates a metal reflecting the `albedo`, whose reflections are blurred by `fuzziness` (`0` for a mirror)
+    pub fn new(albedo: Color, fuzziness: f64) -> MetalMaterial {
         MetalMaterial { albedo, fuzziness }
     }
 }
 
 impl Scatter for MetalMaterial {
-    fn scatter(&self, ray: &Ray, intersection: &IntersectionInfo) -> Option<(Option<Ray>, Color)> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        intersection: &IntersectionInfo,
+        sample: BsdfSample,
+    ) -> Option<(Option<Ray>, Color)> {
         let reflected = ray.direction.normalized().reflect(&intersection.normal);
-        let scattered = Ray::new(
+        let scattered = Ray::with_time(
             intersection.point,
-            reflected + Vector3::random_in_unit_sphere() * self.fuzziness,
+            reflected + Vector3::sample_in_unit_sphere(sample.u, sample.u_choice) * self.fuzziness,
+            ray.time,
         );
         let attenuation = self.albedo;
 
@@ -194,6 +342,17 @@ pub struct TextureMaterial {
 }
 
 impl TextureMaterial {
+    /// Creates a diffuse material whose albedo is looked up in the image at `texture_path`,
+    /// which is loaded when the scene is [precomputed](crate::raytracer::scene::Scene::precompute)
+    pub fn new(texture_path: String) -> TextureMaterial {
+        TextureMaterial {
+            texture_path,
+            pixel_colors: Vec::new(),
+            width: 0.0,
+            height: 0.0,
+        }
+    }
+
     fn get_albedo(&self, u: f64, v: f64) -> Color {
         let x = self.width * u;
         let y = self.height - (self.height * v);
@@ -231,18 +390,23 @@ impl TextureMaterial {
 }
 
 impl Scatter for TextureMaterial {
-    fn scatter(&self, ray: &Ray, intersection: &IntersectionInfo) -> Option<(Option<Ray>, Color)> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        intersection: &IntersectionInfo,
+        sample: BsdfSample,
+    ) -> Option<(Option<Ray>, Color)> {
         let attenuation = self.get_albedo(intersection.u.unwrap(), intersection.v.unwrap());
         if ray.direction.dot(&intersection.normal) > 0.0 {
             return Some((None, attenuation));
         }
-        let mut scatter_direction = intersection.normal + Vector3::random_unit_vector();
+        let mut scatter_direction = intersection.normal + Vector3::sample_unit_vector(sample.u);
 
         if scatter_direction.near_zero() || intersection.normal.dot(&scatter_direction) <= 0.0 {
             scatter_direction = intersection.normal;
         }
 
-        let scattered = Ray::new(intersection.point, scatter_direction);
+        let scattered = Ray::with_time(intersection.point, scatter_direction, ray.time);
 
         if intersection.u.is_none() || intersection.v.is_none() {
             return None;
@@ -250,6 +414,23 @@ impl Scatter for TextureMaterial {
 
         Some((Some(scattered), attenuation))
     }
+
+    fn eval(&self, _ray: &Ray, intersection: &IntersectionInfo, direction: &Vector3) -> Color {
+        match (intersection.u, intersection.v) {
+            (Some(u), Some(v)) => {
+                lambertian_eval(self.get_albedo(u, v), &intersection.normal, direction)
+            }
+            _ => Color::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    fn pdf(&self, _ray: &Ray, intersection: &IntersectionInfo, direction: &Vector3) -> f64 {
+        lambertian_pdf(&intersection.normal, direction)
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
 }
 
 #[cfg(test)]
@@ -266,7 +447,14 @@ mod test {
         },
     };
 
-    use super::{DielectricsMaterial, EmissiveMaterial, Material, Scatter};
+    use super::{BsdfSample, DielectricsMaterial, EmissiveMaterial, Material, Scatter};
+
+    fn test_sample() -> BsdfSample {
+        BsdfSample {
+            u: (0.3, 0.7),
+            u_choice: 0.5,
+        }
+    }
 
     #[test]
     fn test_refract() {
@@ -299,7 +487,7 @@ mod test {
             0.0,
         );
 
-        let result = material.scatter(&ray, &intersection);
+        let result = material.scatter(&ray, &intersection, test_sample());
         assert!(result.is_some());
         if let Some((r, c)) = result {
             assert!(r.is_none());
@@ -320,7 +508,7 @@ mod test {
             0.0,
         );
 
-        let result = material.scatter(&ray, &intersection);
+        let result = material.scatter(&ray, &intersection, test_sample());
         assert!(result.is_some());
         if let Some((r, c)) = result {
             assert!(r.is_some());
@@ -341,7 +529,7 @@ mod test {
             0.0,
         );
 
-        let result = material.scatter(&ray, &intersection);
+        let result = material.scatter(&ray, &intersection, test_sample());
         assert!(result.is_none());
         let intersection = IntersectionInfo::new(
             Vector3::new(0.0, 0.0, 0.0),
@@ -349,7 +537,7 @@ mod test {
             &mat_wrapper,
             0.0,
         );
-        let result = material.scatter(&ray, &&intersection);
+        let result = material.scatter(&ray, &intersection, test_sample());
         if let Some((r, c)) = result {
             assert!(r.is_some());
             assert_eq!(c, Color::new(1.0, 0.5, 0.0));
@@ -369,7 +557,7 @@ mod test {
             0.0,
         );
 
-        let result = material.scatter(&ray, &intersection);
+        let result = material.scatter(&ray, &intersection, test_sample());
         assert!(result.is_some());
         if let Some((r, c)) = result {
             assert!(r.is_some());
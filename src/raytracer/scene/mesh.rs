@@ -1,11 +1,15 @@
 use crate::math::Vector3;
 use serde::Deserialize;
 use std::{
-    collections::HashMap, fmt::Debug, fs::File, io::BufRead, io::BufReader, iter::Peekable,
+    collections::HashMap,
+    fmt::Debug,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    iter::Peekable,
     str::FromStr,
 };
 
-use super::materials::Material;
+use super::{bvh::Bvh, materials::Material};
 
 /// Loads an .obj file and returns a Vec containing all included meshes and their information
 /// needed for raytracing.
@@ -15,12 +19,19 @@ use super::materials::Material;
 /// * `file_path` Path to the .obj file
 /// * `materials` Map containing materials by name listed below the mesh (replacing the materials usually stored in a .mtl file)
 ///
+/// # Errors
+///
+/// If the object file can not be read
+///
 /// # Panics
 ///
-/// If the object file can not be read, materials listed in the .obj file are not in the given map or the object has non-triangulated faces
+/// If materials listed in the .obj file are not in the given map or the object has non-triangulated faces
 /// the function will panic.
-pub fn load_obj(file_path: &std::path::Path, materials: &HashMap<String, Material>) -> Vec<Mesh> {
-    let obj_file = File::open(file_path).unwrap();
+pub fn load_obj(
+    file_path: &std::path::Path,
+    materials: &HashMap<String, Material>,
+) -> io::Result<Vec<Mesh>> {
+    let obj_file = File::open(file_path)?;
     let reader = BufReader::new(obj_file);
 
     let mut result = Vec::new();
@@ -31,7 +42,7 @@ pub fn load_obj(file_path: &std::path::Path, materials: &HashMap<String, Materia
     let mut material_index = usize::MAX;
 
     for line in reader.lines() {
-        let l = line.unwrap();
+        let l = line?;
         let mut values = l.split_whitespace().peekable();
         let header = values.next();
         match header {
@@ -76,7 +87,7 @@ pub fn load_obj(file_path: &std::path::Path, materials: &HashMap<String, Materia
         }
     }
 
-    result
+    Ok(result)
 }
 
 /// Enum representing the different formats of face-descriptions
@@ -299,6 +310,15 @@ pub struct Mesh {
     pub normals: Vec<Vector3>,
     pub uvs: Vec<(f64, f64)>,
     pub aabb: Option<AABB>,
+    pub bvh: Option<Bvh>,
+    /// Translation of the mesh over the course of a frame, the geometry itself describes the start position
+    pub motion: Option<Vector3>,
+}
+
+impl Default for Mesh {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Mesh {
@@ -311,22 +331,53 @@ impl Mesh {
             normals: Vec::new(),
             uvs: Vec::new(),
             aabb: None,
+            bvh: None,
+            motion: None,
+        }
+    }
+
+    /// Returns the translation of the mesh from its start position at the given point in time
+    pub fn offset_at(&self, time: f64) -> Vector3 {
+        match self.motion {
+            Some(motion) => motion * time,
+            None => Vector3::new(0.0, 0.0, 0.0),
         }
     }
 
     /// Computes the AABB of the mesh and stores it in itsself
     pub fn compute_aabb(&mut self) {
-        let mut bb_min = Vector3::new(f64::MAX, f64::MAX, f64::MAX);
-        let mut bb_max = Vector3::new(f64::MIN, f64::MIN, f64::MIN);
+        let mut aabb = AABB::empty();
         for vp in &self.vertex_positions {
-            bb_min = bb_min.min(vp);
-            bb_max = bb_max.max(vp);
+            aabb = aabb.grow(vp);
         }
-        self.aabb = Some(AABB::new(bb_min, bb_max))
+        self.aabb = Some(aabb)
+    }
+
+    /// Builds the BVH over all triangles of the mesh and stores it in itsself
+    pub fn build_bvh(&mut self) {
+        let bounds = (0..self.triangles.len())
+            .map(|idx| (idx, self.triangle_aabb(idx)))
+            .collect();
+        self.bvh = Some(Bvh::new(bounds));
+    }
+
+    /// Computes the AABB of a single triangle of the mesh
+    ///
+    /// # Arguments
+    ///
+    /// * `triangle_idx` index of the triangle in the mesh's triangle list
+    pub fn triangle_aabb(&self, triangle_idx: usize) -> AABB {
+        self.triangles[triangle_idx]
+            .vertex_idx
+            .iter()
+            .fold(AABB::empty(), |aabb, &idx| {
+                aabb.grow(&self.vertex_positions[idx])
+            })
     }
 }
 
 /// Representation of an axis-aligned bounding box
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug)]
 pub struct AABB {
     pub min: Vector3,
@@ -343,6 +394,67 @@ impl AABB {
     pub fn new(min: Vector3, max: Vector3) -> AABB {
         AABB { min, max }
     }
+
+    /// Creates an empty AABB which can be grown by [union](Self::union) or [grow](Self::grow)
+    pub fn empty() -> AABB {
+        AABB::new(
+            Vector3::new(f64::MAX, f64::MAX, f64::MAX),
+            Vector3::new(f64::MIN, f64::MIN, f64::MIN),
+        )
+    }
+
+    /// Returns the smallest AABB containing both `self` and `other`
+    ///
+    /// # Arguments
+    ///
+    /// * `other` AABB to enclose additionally to `self`
+    pub fn union(&self, other: &AABB) -> AABB {
+        AABB::new(self.min.min(&other.min), self.max.max(&other.max))
+    }
+
+    /// Returns the smallest AABB containing both `self` and the given point
+    ///
+    /// # Arguments
+    ///
+    /// * `point` point to enclose additionally to `self`
+    pub fn grow(&self, point: &Vector3) -> AABB {
+        AABB::new(self.min.min(point), self.max.max(point))
+    }
+
+    /// Returns the AABB moved by the given offset
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` translation to apply to the AABB
+    pub fn translate(&self, offset: &Vector3) -> AABB {
+        AABB::new(self.min + *offset, self.max + *offset)
+    }
+
+    /// Returns the center point of the AABB
+    pub fn centroid(&self) -> Vector3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Returns the surface area of the AABB or `0.0` if the AABB is empty
+    pub fn surface_area(&self) -> f64 {
+        let extent = self.max - self.min;
+        if extent[0] < 0.0 || extent[1] < 0.0 || extent[2] < 0.0 {
+            return 0.0;
+        }
+        2.0 * (extent[0] * extent[1] + extent[1] * extent[2] + extent[2] * extent[0])
+    }
+
+    /// Returns the index of the axis along which the AABB is the longest
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.max - self.min;
+        if extent[0] > extent[1] && extent[0] > extent[2] {
+            0
+        } else if extent[1] > extent[2] {
+            1
+        } else {
+            2
+        }
+    }
 }
 
 /// Triangle acts as an index struct representing a single triangle of a mesh.
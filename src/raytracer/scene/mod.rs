@@ -1,6 +1,9 @@
-mod intersections;
+mod bvh;
+pub mod intersections;
+pub mod lights;
 pub mod materials;
-mod mesh;
+pub mod mesh;
+#[allow(clippy::module_inception)]
 mod scene;
 
 pub use scene::*;
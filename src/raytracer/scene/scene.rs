@@ -6,13 +6,16 @@ use crate::{
     raytracer::{
         image::{self, Color},
         raytrace::Ray,
+        stats,
     },
 };
 
 use super::{
-    intersections::{Intersectable, IntersectionInfo},
+    bvh::{Bounded, Bvh},
+    intersections::{closest_intersection, Intersectable, IntersectionInfo},
+    lights::{AreaLight, EnvironmentConfig, EnvironmentLight, Light, LightSample},
     materials::Material,
-    mesh::{self, Mesh},
+    mesh::{self, Mesh, AABB},
 };
 
 #[derive(Deserialize)]
@@ -21,40 +24,212 @@ pub struct Scene {
     pub width: usize,
     pub height: usize,
     pub background: Color,
-    #[serde(skip_deserializing)]
+    /// Optional environment map which replaces the constant `background` for rays leaving the scene
+    #[serde(default)]
+    pub environment: Option<EnvironmentConfig>,
+    /// Analytic lights configured in the scene, area lights of emissive objects are added in [precompute](Self::precompute)
+    #[serde(default)]
     pub lights: Vec<Light>,
     pub objects: Vec<Object>,
+    /// BVH over all bounded objects, built in [precompute](Self::precompute)
+    #[serde(skip_deserializing)]
+    bvh: Option<Bvh>,
+    /// Indices of objects without a bounding box (e.g. planes) which are tested separately
+    #[serde(skip_deserializing)]
+    unbounded_objects: Vec<usize>,
 }
 
 impl Scene {
+    /// Creates a scene without analytic lights and environment map, which can be added to `lights` and
+    /// `environment` afterwards. The scene has to be [precomputed](Self::precompute) before it is rendered.
+    ///
+    /// # Arguments
+    ///
+    /// * `camera` configuration of the camera
+    /// * `width` width of the image
+    /// * `height` height of the image
+    /// * `background` radiance of rays leaving the scene
+    /// * `objects` objects of the scene, emissive objects become area lights
+    pub fn new(
+        camera: CameraConfig,
+        width: usize,
+        height: usize,
+        background: Color,
+        objects: Vec<Object>,
+    ) -> Scene {
+        Scene {
+            camera,
+            width,
+            height,
+            background,
+            environment: None,
+            lights: Vec::new(),
+            objects,
+            bvh: None,
+            unbounded_objects: Vec::new(),
+        }
+    }
+
+    /// Parses a scene from its YAML description (see the README), loading the meshes it references.
+    /// The scene has to be [precomputed](Self::precompute) before it is rendered.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` YAML description of the scene
+    ///
+    /// # Errors
+    ///
+    /// If the description is no valid scene or a referenced mesh file can't be read
+    ///
+    /// # Panics
+    ///
+    /// If a mesh file is malformed (see [load_obj](mesh::load_obj)).
+    pub fn from_yaml(source: &str) -> Result<Scene, serde_yaml::Error> {
+        serde_yaml::from_str(source)
+    }
+
     /// Returns the closest intersection of the ray with an object of the scene if there is any.
+    /// If the scene has been precomputed, bounded objects are looked up in the BVH and only unbounded
+    /// objects are tested linearly.
     ///
     /// # Arguments
     ///
     /// * `ray` the ray for which to check intersections
-    pub fn get_closest_interesection(&self, ray: &Ray) -> Option<IntersectionInfo> {
-        let mut info: Option<IntersectionInfo> = None;
-
-        for o in &self.objects {
-            let intersection = o.intersect(ray);
-            if let Some(intersection_info) = intersection {
-                match info {
-                    Some(i) => {
-                        if i.t > intersection_info.t {
-                            info = Some(intersection_info)
-                        }
-                    }
-                    None => info = Some(intersection_info),
-                }
+    pub fn get_closest_interesection(&self, ray: &Ray) -> Option<IntersectionInfo<'_>> {
+        stats::count_closest_hit_ray();
+        match &self.bvh {
+            Some(bvh) => {
+                let info = bvh.intersect(ray, |idx| self.intersect_object(ray, idx));
+                self.unbounded_objects.iter().fold(info, |info, &idx| {
+                    closest_intersection(info, self.intersect_object(ray, idx))
+                })
+            }
+            None => (0..self.objects.len()).fold(None, |info, idx| {
+                closest_intersection(info, self.intersect_object(ray, idx))
+            }),
+        }
+    }
+
+    /// Intersects the ray with a single object and sets the object index of the intersection
+    fn intersect_object(&self, ray: &Ray, object_idx: usize) -> Option<IntersectionInfo<'_>> {
+        self.objects[object_idx]
+            .intersect(ray)
+            .map(|info| IntersectionInfo { object_idx, ..info })
+    }
+
+    /// Samples a light of the scene uniformly and a direction from `point` towards it.
+    /// The pdf of the returned sample includes the probability of choosing the light.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` the point to sample a light from
+    /// * `time` point in time at which moving lights are sampled
+    /// * `u_light` uniform sample choosing the light
+    /// * `u` uniform sample choosing the direction towards the light
+    pub fn sample_light(
+        &self,
+        point: &Vector3,
+        time: f64,
+        u_light: f64,
+        u: (f64, f64),
+    ) -> Option<LightSample> {
+        if self.lights.is_empty() {
+            return None;
+        }
+        let light_idx = ((u_light * self.lights.len() as f64) as usize).min(self.lights.len() - 1);
+        let mut sample = self.lights[light_idx].sample(&self.objects, point, time, u)?;
+        sample.pdf /= self.lights.len() as f64;
+        Some(sample)
+    }
+
+    /// Returns the pdf with which [sample_light](Self::sample_light) would have sampled the direction of `ray`
+    /// towards the intersected point, or `0.0` if the intersected object is no light.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` ray which hit an emissive object
+    /// * `intersection` intersection of the ray returned by [get_closest_interesection](Self::get_closest_interesection)
+    pub fn light_pdf(&self, ray: &Ray, intersection: &IntersectionInfo) -> f64 {
+        let area_light = self.lights.iter().find_map(|l| match l {
+            Light::Area(area_light) if area_light.object_idx == intersection.object_idx => {
+                Some(area_light)
+            }
+            _ => None,
+        });
+        match area_light {
+            Some(light) => {
+                light.pdf(&self.objects[light.object_idx], ray, intersection)
+                    / self.lights.len() as f64
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Returns the radiance arriving along a ray which left the scene, which is either looked up in the
+    /// environment map or the constant background color.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` ray which didn't hit any object
+    pub fn background_color(&self, ray: &Ray) -> Color {
+        match self.environment_light() {
+            Some(environment) => environment.radiance(&ray.direction),
+            None => self.background,
+        }
+    }
+
+    /// Returns the pdf with which [sample_light](Self::sample_light) would have sampled the direction of `ray`
+    /// on the environment map, or `0.0` if the scene has no environment map.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` ray which didn't hit any object
+    pub fn environment_pdf(&self, ray: &Ray) -> f64 {
+        match self.environment_light() {
+            Some(environment) => environment.pdf(&ray.direction) / self.lights.len() as f64,
+            None => 0.0,
+        }
+    }
+
+    fn environment_light(&self) -> Option<&EnvironmentLight> {
+        self.lights.iter().find_map(|l| match l {
+            Light::Environment(environment) => Some(environment),
+            _ => None,
+        })
+    }
+
+    /// Checks if any object of the scene blocks the ray before the distance `max_t`.
+    /// In contrast to [get_closest_interesection](Self::get_closest_interesection) the search stops at the first
+    /// blocker found, which makes this the preferred query for shadow rays and other visibility tests.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` the ray for which to check occlusion
+    /// * `max_t` distance along the ray up to which objects are considered blocking
+    pub fn is_occluded(&self, ray: &Ray, max_t: f64) -> bool {
+        stats::count_shadow_ray();
+        match &self.bvh {
+            Some(bvh) => {
+                bvh.any_hit(ray, max_t, |idx| self.objects[idx].is_occluded(ray, max_t))
+                    || self
+                        .unbounded_objects
+                        .iter()
+                        .any(|&idx| self.objects[idx].is_occluded(ray, max_t))
             }
+            None => self.objects.iter().any(|o| o.is_occluded(ray, max_t)),
         }
-        info
     }
 
+    /// Loads the textures of the objects, adds the area lights of emissive objects and the environment light and
+    /// builds the BVH. Has to be called before the scene is rendered and again after objects, lights or the
+    /// environment were changed, which replaces the area and environment lights added before.
     pub fn precompute(&mut self) {
-        for o in &mut self.objects {
+        self.lights
+            .retain(|light| !matches!(light, Light::Area(_) | Light::Environment(_)));
+        for (idx, o) in self.objects.iter_mut().enumerate() {
             if let Object::Mesh(mesh) = o {
                 mesh.compute_aabb();
+                mesh.build_bvh();
                 for mat in &mut mesh.materials {
                     if let Material::Texture(tm) = mat {
                         let (pixels, width, height) =
@@ -66,18 +241,263 @@ impl Scene {
                 }
             }
             if o.is_light() {
-                self.lights.push(Light::from(&*o));
+                let light = AreaLight::from_object(idx, o);
+                // meshes with an emissive material that none of their faces uses have nothing to sample
+                if light.area > 0.0 {
+                    self.lights.push(Light::Area(light));
+                }
             }
         }
+        if let Some(config) = &self.environment {
+            self.lights
+                .push(Light::Environment(EnvironmentLight::new(config)));
+        }
+        self.build_bvh();
+    }
+
+    /// Returns `true` if the scene has been [precomputed](Self::precompute)
+    pub fn is_precomputed(&self) -> bool {
+        self.bvh.is_some()
+    }
+
+    /// Builds the BVH over all objects with a bounding box and collects the remaining unbounded objects.
+    fn build_bvh(&mut self) {
+        let mut bounded: Vec<(usize, AABB)> = Vec::new();
+        self.unbounded_objects.clear();
+        for (idx, o) in self.objects.iter().enumerate() {
+            match o.bounding_box() {
+                Some(aabb) => bounded.push((idx, aabb)),
+                None => self.unbounded_objects.push(idx),
+            }
+        }
+        self.bvh = Some(Bvh::new(bounded));
     }
 }
 
+/// Configuration of the camera.
+/// A positive `aperture` (diameter of the lens) enables depth of field with objects at `focus_distance`
+/// from the eye being in focus. The focus distance defaults to the distance between `eye` and `look_at`.
+/// The shutter is open in the interval `[shutter_open, shutter_close]` (default `[0, 1]`) of the frame time in which
+/// moving objects travel from their start to their end position.
+/// With an orthographic `projection` the visible height of the image is given by `ortho_height` instead of `fovy`,
+/// it defaults to the height of the perspective frustum at the distance of `look_at`.
+/// Fisheye cameras cover `fisheye_fov` degrees (default 180) across their image circle.
+/// A `physical` camera description replaces `fovy` and `aperture` by the values derived from its lens and sensor
+/// and additionally scales the image by its exposure (see [field_of_view](Self::field_of_view),
+/// [lens_diameter](Self::lens_diameter) and [exposure](Self::exposure)).
+/// With a `stereo` configuration a left and right view are rendered and combined into a single image.
 #[derive(Deserialize)]
 pub struct CameraConfig {
     pub eye: Vector3,
     pub look_at: Vector3,
     pub up: Vector3,
+    #[serde(default = "default_fovy")]
     pub fovy: f64,
+    #[serde(default)]
+    pub projection: Projection,
+    #[serde(default)]
+    pub ortho_height: Option<f64>,
+    #[serde(default = "default_fisheye_fov")]
+    pub fisheye_fov: f64,
+    #[serde(default)]
+    pub aperture: f64,
+    #[serde(default)]
+    pub focus_distance: Option<f64>,
+    #[serde(default)]
+    pub shutter_open: f64,
+    #[serde(default = "default_shutter_close")]
+    pub shutter_close: f64,
+    #[serde(default)]
+    pub physical: Option<PhysicalCameraConfig>,
+    #[serde(default)]
+    pub stereo: Option<StereoConfig>,
+}
+
+impl CameraConfig {
+    /// Creates a perspective pinhole camera with a vertical field of view of 45 degrees whose shutter is open over
+    /// the whole frame. The other options can be changed on the returned configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `eye` position of the camera
+    /// * `look_at` point in the center of the image
+    /// * `up` direction pointing upwards in the image
+    pub fn new(eye: Vector3, look_at: Vector3, up: Vector3) -> CameraConfig {
+        CameraConfig {
+            eye,
+            look_at,
+            up,
+            fovy: default_fovy(),
+            projection: Projection::default(),
+            ortho_height: None,
+            fisheye_fov: default_fisheye_fov(),
+            aperture: 0.0,
+            focus_distance: None,
+            shutter_open: 0.0,
+            shutter_close: default_shutter_close(),
+            physical: None,
+            stereo: None,
+        }
+    }
+
+    /// Returns the vertical field of view in degrees
+    pub fn field_of_view(&self) -> f64 {
+        match &self.physical {
+            Some(physical) => physical.field_of_view(),
+            None => self.fovy,
+        }
+    }
+
+    /// Returns the diameter of the lens in scene units, `0` for a pinhole camera
+    pub fn lens_diameter(&self) -> f64 {
+        match &self.physical {
+            Some(physical) => physical.lens_diameter(),
+            None => self.aperture,
+        }
+    }
+
+    /// Returns the factor by which the radiance arriving at the camera is scaled in the image
+    pub fn exposure(&self) -> f64 {
+        match &self.physical {
+            Some(physical) => physical.exposure(),
+            None => 1.0,
+        }
+    }
+}
+
+fn default_fovy() -> f64 {
+    45.0
+}
+
+fn default_shutter_close() -> f64 {
+    1.0
+}
+
+/// Description of a real camera by the lens and sensor used and its exposure triangle.
+/// Lengths of the lens and sensor are given in millimeters while scene units are assumed to be meters.
+/// The radiance of the scene is interpreted as luminance in cd/m² when computing the exposure.
+/// The focal length, sensor size, f-number, shutter time and ISO have to be positive, other values are rejected when
+/// deserializing.
+#[derive(Deserialize, Clone)]
+pub struct PhysicalCameraConfig {
+    /// Focal length of the lens in mm
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub focal_length: f64,
+    /// Width and height of the sensor in mm (default full frame 36 x 24)
+    #[serde(
+        default = "default_sensor_size",
+        deserialize_with = "deserialize_positive_size"
+    )]
+    pub sensor_size: [f64; 2],
+    /// Ratio of the focal length to the diameter of the aperture
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub f_number: f64,
+    /// Time the shutter is open in seconds, which only affects the exposure.
+    /// The frame has no duration in seconds, so the interval sampled for motion blur is still given by
+    /// `shutter_open` and `shutter_close` of the [CameraConfig].
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub shutter_time: f64,
+    /// Sensitivity of the sensor
+    #[serde(deserialize_with = "deserialize_positive")]
+    pub iso: f64,
+}
+
+impl PhysicalCameraConfig {
+    /// Returns the vertical field of view in degrees covered by the sensor height
+    pub fn field_of_view(&self) -> f64 {
+        (2.0 * f64::atan(0.5 * self.sensor_size[1] / self.focal_length)).to_degrees()
+    }
+
+    /// Returns the diameter of the aperture in meters
+    pub fn lens_diameter(&self) -> f64 {
+        self.focal_length / self.f_number * 1e-3
+    }
+
+    /// Returns the exposure scale following the exposure value at ISO 100
+    /// `EV100 = log2(N² / t * 100 / ISO)` and a saturation based sensor with `exposure = 1 / (1.2 * 2^EV100)`.
+    pub fn exposure(&self) -> f64 {
+        let ev100 = f64::log2(self.f_number * self.f_number / self.shutter_time * 100.0 / self.iso);
+        1.0 / (1.2 * f64::powf(2.0, ev100))
+    }
+}
+
+fn default_sensor_size() -> [f64; 2] {
+    [36.0, 24.0]
+}
+
+/// Deserializes a finite number greater than zero
+fn deserialize_positive<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = f64::deserialize(deserializer)?;
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "expected a positive number, got {}",
+            value
+        )))
+    }
+}
+
+/// Deserializes a width and height which are both finite numbers greater than zero
+fn deserialize_positive_size<'de, D>(deserializer: D) -> Result<[f64; 2], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let size = <[f64; 2]>::deserialize(deserializer)?;
+    if size.iter().all(|value| *value > 0.0 && value.is_finite()) {
+        Ok(size)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "expected a positive width and height, got [{}, {}]",
+            size[0], size[1]
+        )))
+    }
+}
+
+/// Configuration of a stereo camera pair.
+/// Both eyes are moved apart from the configured eye by half the `interocular_distance` and look in parallel,
+/// their images are shifted so that objects at the `convergence` distance appear on the screen plane.
+/// The convergence defaults to the focus distance of the camera.
+#[derive(Deserialize, Clone)]
+pub struct StereoConfig {
+    pub interocular_distance: f64,
+    #[serde(default)]
+    pub convergence: Option<f64>,
+    #[serde(default)]
+    pub layout: StereoLayout,
+}
+
+/// Arrangement of the left and right view in the output image, each view has the full image size
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StereoLayout {
+    /// Left view on the left and right view on the right half of an image twice as wide
+    #[default]
+    SideBySide,
+    /// Left view on the top and right view on the bottom half of an image twice as high
+    OverUnder,
+}
+
+fn default_fisheye_fov() -> f64 {
+    180.0
+}
+
+/// Projection of the camera onto the image plane
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Projection {
+    /// Rays start at the eye and spread out through the viewing frustum
+    #[default]
+    Perspective,
+    /// Rays run parallel along the viewing direction and start across the image plane through the eye
+    Orthographic,
+    /// Full 360 degree panorama in latitude / longitude layout around the eye
+    Equirectangular,
+    /// Equidistant fisheye projection inside a circle in the image center
+    Fisheye,
 }
 
 #[derive(Deserialize, Clone)]
@@ -89,16 +509,10 @@ pub enum Object {
 }
 
 impl Object {
-    fn is_light(&self) -> bool {
+    pub fn is_light(&self) -> bool {
         match self {
-            Object::Sphere(sphere) => match sphere.material {
-                Material::Emissive(_) => true,
-                _ => false,
-            },
-            Object::Plane(plane) => match plane.material {
-                Material::Emissive(_) => true,
-                _ => false,
-            },
+            Object::Sphere(sphere) => matches!(sphere.material, Material::Emissive(_)),
+            Object::Plane(plane) => matches!(plane.material, Material::Emissive(_)),
             Object::Mesh(mesh) => {
                 for material in &mesh.materials {
                     if let Material::Emissive(_) = material {
@@ -111,71 +525,79 @@ impl Object {
     }
 }
 
-pub struct Light {
-    pub sample_points: Vec<Vector3>,
-}
-
-impl Light {
-    fn new(sample_points: Vec<Vector3>) -> Light {
-        Light { sample_points }
+impl Intersectable for Object {
+    fn intersect(&self, ray: &Ray) -> Option<IntersectionInfo<'_>> {
+        match self {
+            Object::Sphere(sphere) => sphere.intersect(ray),
+            Object::Plane(plane) => plane.intersect(ray),
+            Object::Mesh(mesh) => mesh.intersect(ray),
+        }
     }
-}
 
-impl From<&Object> for Light {
-    fn from(o: &Object) -> Self {
-        match o {
-            Object::Plane(p) => Light::new(vec![p.center]),
-            Object::Sphere(s) => Light::new(vec![s.center]),
-            Object::Mesh(m) => {
-                let mut color = Color::new(0.0, 0.0, 0.0);
-                let mut sample_positions = Vec::new();
-                for triangle in &m.triangles {
-                    if let Material::Emissive(e) = &m.materials[triangle.material_idx] {
-                        color += e.color;
-                        let interpolated = (m.vertex_positions[triangle.vertex_idx[0]]
-                            + m.vertex_positions[triangle.vertex_idx[1]]
-                            + m.vertex_positions[triangle.vertex_idx[2]])
-                            / 3.0;
-                        sample_positions.push(interpolated);
-                    }
-                }
-                Light::new(sample_positions)
-            }
+    fn is_occluded(&self, ray: &Ray, max_t: f64) -> bool {
+        match self {
+            Object::Sphere(sphere) => sphere.is_occluded(ray, max_t),
+            Object::Plane(plane) => plane.is_occluded(ray, max_t),
+            Object::Mesh(mesh) => mesh.is_occluded(ray, max_t),
         }
     }
 }
 
-impl Intersectable for Object {
-    fn intersect(&self, ray: &Ray) -> Option<IntersectionInfo> {
+impl Bounded for Object {
+    fn bounding_box(&self) -> Option<AABB> {
         match self {
-            Object::Sphere(sphere) => sphere.intersect(ray),
-            Object::Plane(plane) => plane.intersect(ray),
-            Object::Mesh(mesh) => mesh.intersect(ray),
+            Object::Sphere(sphere) => sphere.bounding_box(),
+            Object::Plane(plane) => plane.bounding_box(),
+            Object::Mesh(mesh) => mesh.bounding_box(),
         }
     }
 }
 
+/// Description of a mesh in the scene file, whose geometry is loaded from the `.obj` file at `path`
+#[derive(Deserialize)]
+struct MeshConfig {
+    path: String,
+    materials: HashMap<String, Material>,
+    #[serde(default)]
+    motion: Option<Vector3>,
+}
+
 impl<'de> Deserialize<'de> for Mesh {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let val: serde_yaml::Value = serde_yaml::Value::deserialize(deserializer).unwrap();
-        let path = std::path::Path::new(val.get("path").unwrap().as_str().unwrap());
-        let materials = val.get("materials").unwrap();
-
-        let materials: HashMap<String, Material> =
-            serde_yaml::from_value(materials.clone()).unwrap();
-        let meshes = mesh::load_obj(path, &materials);
-        Ok(meshes[0].to_owned())
+        let config = MeshConfig::deserialize(deserializer)?;
+        let meshes =
+            mesh::load_obj(Path::new(&config.path), &config.materials).map_err(|error| {
+                serde::de::Error::custom(format!("can't read mesh {}: {}", config.path, error))
+            })?;
+        let mut mesh = meshes.into_iter().next().ok_or_else(|| {
+            serde::de::Error::custom(format!("mesh {} contains no object", config.path))
+        })?;
+        mesh.motion = config.motion;
+        Ok(mesh)
     }
 }
 
+/// Sphere which optionally moves by `motion` over the course of a frame (see [Ray::time])
 #[derive(Deserialize, Clone)]
 pub struct Sphere {
     pub center: Vector3,
     pub radius: f64,
     pub material: Material,
+    #[serde(default)]
+    pub motion: Option<Vector3>,
+}
+
+impl Sphere {
+    /// Returns the center of the sphere at the given point in time
+    pub fn center_at(&self, time: f64) -> Vector3 {
+        match self.motion {
+            Some(motion) => self.center + motion * time,
+            None => self.center,
+        }
+    }
 }
 
 #[derive(Deserialize, Clone)]
@@ -200,34 +622,52 @@ mod test {
         },
     };
 
-    use super::{Light, Plane, Scene, Sphere};
+    use super::{AreaLight, Light, PhysicalCameraConfig, Plane, Projection, Scene, Sphere};
 
-    #[test]
-    fn test_closest_intersection() {
-        let mut scene = Scene {
+    fn create_test_scene() -> Scene {
+        Scene {
             background: Color::new(0.0, 0.0, 0.0),
+            environment: None,
             camera: super::CameraConfig {
                 eye: Vector3::new(0.0, 0.0, 0.0),
                 look_at: Vector3::new(0.0, 0.0, 0.0),
                 up: Vector3::new(0.0, 0.0, 0.0),
                 fovy: 0.0,
+                projection: Projection::Perspective,
+                ortho_height: None,
+                fisheye_fov: 180.0,
+                aperture: 0.0,
+                focus_distance: None,
+                shutter_open: 0.0,
+                shutter_close: 1.0,
+                physical: None,
+                stereo: None,
             },
             height: 10,
             width: 10,
             lights: Vec::new(),
             objects: Vec::new(),
-        };
+            bvh: None,
+            unbounded_objects: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_closest_intersection() {
+        let mut scene = create_test_scene();
         let material = Material::Emissive(EmissiveMaterial::new(Color::new(0.0, 0.0, 0.0)));
         let sphere1 = Object::Sphere(Sphere {
             center: Vector3::new(5.0, 0.0, 0.0),
             radius: 1.0,
             material: material.clone(),
+            motion: None,
         });
         scene.objects.push(sphere1);
         let sphere2 = Object::Sphere(Sphere {
             center: Vector3::new(5.0, 0.0, 0.0),
             radius: 1.5,
             material: material.clone(),
+            motion: None,
         });
         scene.objects.push(sphere2);
         let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
@@ -240,11 +680,172 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_closest_intersection_bvh() {
+        let mut scene = create_test_scene();
+        let material = Material::Lambertian(LambertianMaterial::new(Color::new(0.0, 0.0, 0.0)));
+        for i in 0..10 {
+            scene.objects.push(Object::Sphere(Sphere {
+                center: Vector3::new(3.0 + i as f64, (i % 3) as f64 - 1.0, 0.0),
+                radius: 0.6,
+                material: material.clone(),
+                motion: None,
+            }));
+        }
+        scene.objects.push(Object::Plane(Plane {
+            center: Vector3::new(7.5, 0.0, 0.0),
+            normal: Vector3::new(-1.0, 0.0, 0.0),
+            material: material.clone(),
+        }));
+
+        let rays: Vec<Ray> = (0..50)
+            .map(|i| {
+                Ray::new(
+                    Vector3::new(0.0, 0.0, 0.0),
+                    Vector3::new(1.0, (i as f64 - 25.0) * 0.01, (i % 5) as f64 * 0.02),
+                )
+            })
+            .collect();
+        let expected: Vec<Option<f64>> = rays
+            .iter()
+            .map(|ray| scene.get_closest_interesection(ray).map(|i| i.t))
+            .collect();
+
+        scene.precompute();
+
+        assert!(scene.bvh.is_some());
+        assert_eq!(scene.unbounded_objects, vec![10]);
+        for (ray, expected) in rays.iter().zip(expected) {
+            let actual = scene.get_closest_interesection(ray).map(|i| i.t);
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_is_occluded() {
+        let mut scene = create_test_scene();
+        let material = Material::Lambertian(LambertianMaterial::new(Color::new(0.0, 0.0, 0.0)));
+        scene.objects.push(Object::Sphere(Sphere {
+            center: Vector3::new(5.0, 0.0, 0.0),
+            radius: 1.0,
+            material: material.clone(),
+            motion: None,
+        }));
+        scene.objects.push(Object::Plane(Plane {
+            center: Vector3::new(0.0, -2.0, 0.0),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+            material: material.clone(),
+        }));
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let ray_down = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let ray_up = Ray::new(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+
+        for precomputed in [false, true] {
+            if precomputed {
+                scene.precompute();
+            }
+            assert!(scene.is_occluded(&ray, f64::MAX));
+            assert!(scene.is_occluded(&ray, 4.5));
+            assert!(!scene.is_occluded(&ray, 3.5));
+            assert!(scene.is_occluded(&ray_down, 3.0));
+            assert!(!scene.is_occluded(&ray_down, 1.5));
+            assert!(!scene.is_occluded(&ray_up, f64::MAX));
+        }
+    }
+
+    #[test]
+    fn test_deserialize_lights() {
+        let yaml = "
+width: 10
+height: 10
+background: [0, 0, 0]
+camera:
+  eye: [0, 0, 5]
+  look_at: [0, 0, 0]
+  up: [0, 1, 0]
+  fovy: 45
+lights:
+  - type: Point
+    position: [0, 5, 0]
+    color: [1, 1, 1]
+    intensity: 20
+  - type: Spot
+    position: [0, 5, 0]
+    direction: [0, -1, 0]
+    color: [1, 0.9, 0.8]
+    outer_angle: 30
+  - type: Directional
+    direction: [1, -1, 0]
+    color: [1, 1, 1]
+objects:
+  - type: Sphere
+    center: [0, 0, 0]
+    radius: 1
+    material:
+      type: Emissive
+      color: [1, 1, 1]
+";
+        let mut scene: Scene = serde_yaml::from_str(yaml).unwrap();
+        scene.precompute();
+
+        assert_eq!(scene.lights.len(), 4);
+        assert!(matches!(&scene.lights[0], Light::Point(l) if l.intensity == 20.0));
+        assert!(
+            matches!(&scene.lights[1], Light::Spot(l) if l.intensity == 1.0 && l.falloff == 1.0)
+        );
+        assert!(matches!(scene.lights[2], Light::Directional(_)));
+        assert!(matches!(&scene.lights[3], Light::Area(l) if l.object_idx == 0));
+
+        // precomputing again replaces the area light instead of adding it twice
+        scene.precompute();
+        assert_eq!(scene.lights.len(), 4);
+        assert!(matches!(&scene.lights[3], Light::Area(l) if l.object_idx == 0));
+    }
+
+    #[test]
+    fn test_deserialize_mesh_errors() {
+        let scene = |mesh: &str| {
+            format!(
+                "
+width: 10
+height: 10
+background: [0, 0, 0]
+camera:
+  eye: [0, 0, 5]
+  look_at: [0, 0, 0]
+  up: [0, 1, 0]
+objects:
+  - type: Mesh
+{}
+",
+                mesh
+            )
+        };
+        let material =
+            "    materials:\n      Material:\n        type: Lambertian\n        albedo: [1, 1, 1]";
+
+        assert!(Scene::from_yaml(&scene(&format!(
+            "    path: scenes/cube/missing.obj\n{}",
+            material
+        )))
+        .is_err());
+        assert!(Scene::from_yaml(&scene(material)).is_err());
+        assert!(Scene::from_yaml(&scene("    path: scenes/cube/cube.obj")).is_err());
+
+        let valid = Scene::from_yaml(&scene(&format!(
+            "    path: scenes/cube/cube.obj\n    motion: [1, 0, 0]\n{}",
+            material
+        )))
+        .unwrap();
+        assert!(matches!(&valid.objects[0], Object::Mesh(mesh) if mesh.motion.is_some()));
+    }
+
     fn create_test_objects(material: &Material) -> (Object, Object, Object) {
         let sphere = Object::Sphere(Sphere {
             center: Vector3::new(0.0, 0.0, 0.0),
             radius: 0.0,
             material: material.clone(),
+            motion: None,
         });
         let plane = Object::Plane(Plane {
             center: Vector3::new(0.0, 0.0, 0.0),
@@ -278,20 +879,92 @@ mod test {
         assert!(!mesh_neg.is_light());
     }
 
+    #[test]
+    fn test_unused_emissive_mesh_material() {
+        let emissive = Material::Emissive(EmissiveMaterial::new(Color::new(1.0, 1.0, 1.0)));
+        let diffuse = Material::Lambertian(LambertianMaterial::new(Color::new(0.5, 0.5, 0.5)));
+        let (_, _, mesh) = create_test_objects(&diffuse);
+        let mesh = match mesh {
+            Object::Mesh(mut mesh) => {
+                mesh.materials.push(emissive);
+                Object::Mesh(mesh)
+            }
+            _ => unreachable!(),
+        };
+        assert!(mesh.is_light());
+
+        let mut scene = create_test_scene();
+        scene.objects.push(mesh);
+        scene.precompute();
+        assert!(scene.lights.is_empty());
+        let point = Vector3::new(1.0, 1.0, 5.0);
+        assert!(scene.sample_light(&point, 0.0, 0.5, (0.5, 0.5)).is_none());
+    }
+
     #[test]
     fn test_light_from_object() {
         let material = Material::Emissive(EmissiveMaterial::new(Color::new(0.0, 0.0, 0.0)));
         let (sphere, plane, mesh) = create_test_objects(&material);
 
-        let sphere = Light::from(&sphere);
-        let plane = Light::from(&plane);
-        let mesh = Light::from(&mesh);
+        let sphere = AreaLight::from_object(0, &sphere);
+        let plane = AreaLight::from_object(1, &plane);
+        let mesh = AreaLight::from_object(2, &mesh);
+
+        assert_eq!(sphere.object_idx, 0);
+        assert_eq!(sphere.area, 0.0);
+        assert_eq!(plane.object_idx, 1);
+        assert_eq!(plane.area, f64::INFINITY);
+        assert_eq!(mesh.object_idx, 2);
+        assert!((mesh.area - 4.5 * f64::sqrt(3.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_physical_camera() {
+        let physical = PhysicalCameraConfig {
+            focal_length: 50.0,
+            sensor_size: [36.0, 24.0],
+            f_number: 2.0,
+            shutter_time: 0.01,
+            iso: 400.0,
+        };
+
+        // 24mm sensor height behind a 50mm lens
+        assert!((physical.field_of_view() - 26.991).abs() < 1e-3);
+        assert!((physical.lens_diameter() - 0.025).abs() < 1e-12);
+        // N² / t * 100 / ISO = 100 -> EV100 = log2(100)
+        assert!((physical.exposure() - 1.0 / 120.0).abs() < 1e-12);
 
-        assert_eq!(sphere.sample_points.len(), 1);
-        assert_eq!(sphere.sample_points[0], Vector3::new(0.0, 0.0, 0.0));
-        assert_eq!(plane.sample_points.len(), 1);
-        assert_eq!(plane.sample_points[0], Vector3::new(0.0, 0.0, 0.0));
-        assert_eq!(mesh.sample_points.len(), 1);
-        assert_eq!(mesh.sample_points[0], Vector3::new(1.0, 1.0, 1.0));
+        let config = |shutter_time: &str, iso: &str| {
+            format!(
+                "{{focal_length: 50, f_number: 2, shutter_time: {}, iso: {}}}",
+                shutter_time, iso
+            )
+        };
+        let physical: PhysicalCameraConfig = serde_yaml::from_str(&config("0.01", "400")).unwrap();
+        assert!((physical.exposure() - 1.0 / 120.0).abs() < 1e-12);
+        for (shutter_time, iso) in [
+            ("0", "400"),
+            ("-0.01", "400"),
+            ("0.01", "0"),
+            ("0.01", ".inf"),
+        ] {
+            assert!(
+                serde_yaml::from_str::<PhysicalCameraConfig>(&config(shutter_time, iso)).is_err()
+            );
+        }
+        assert!(serde_yaml::from_str::<PhysicalCameraConfig>(
+            "{focal_length: 50, f_number: -2, shutter_time: 0.01, iso: 400}"
+        )
+        .is_err());
+        for sensor_size in ["[36, 0]", "[-36, 24]", "[.nan, 24]"] {
+            let yaml = format!(
+                "{{focal_length: 50, sensor_size: {}, f_number: 2, shutter_time: 0.01, iso: 400}}",
+                sensor_size
+            );
+            assert!(serde_yaml::from_str::<PhysicalCameraConfig>(&yaml).is_err());
+        }
+        let yaml = "{focal_length: 50, sensor_size: [23.5, 15.6], f_number: 2, shutter_time: 0.01, iso: 400}";
+        let physical: PhysicalCameraConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(physical.sensor_size, [23.5, 15.6]);
     }
 }
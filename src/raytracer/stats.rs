@@ -0,0 +1,342 @@
+use std::cell::Cell;
+use std::io::Write;
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
+
+use super::progressive::Progressive;
+
+/// Minimum time between two progress reports
+const REPORT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Number of rays traced and intersection tests performed by a render
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct RayStats {
+    /// rays spawned by the camera
+    pub primary_rays: u64,
+    /// rays searched for their closest intersection with the scene, including the primary rays
+    pub closest_hit_rays: u64,
+    /// rays only tested for occlusion, e.g. towards a light
+    pub shadow_rays: u64,
+    /// intersection tests of rays with spheres, planes and triangles
+    pub intersection_tests: u64,
+}
+
+impl RayStats {
+    const ZERO: RayStats = RayStats {
+        primary_rays: 0,
+        closest_hit_rays: 0,
+        shadow_rays: 0,
+        intersection_tests: 0,
+    };
+
+    /// Returns the number of rays scattered or reflected at a surface
+    pub fn secondary_rays(&self) -> u64 {
+        self.closest_hit_rays.saturating_sub(self.primary_rays)
+    }
+
+    /// Returns the number of all rays traced through the scene
+    pub fn total_rays(&self) -> u64 {
+        self.closest_hit_rays + self.shadow_rays
+    }
+}
+
+impl AddAssign for RayStats {
+    fn add_assign(&mut self, other: RayStats) {
+        self.primary_rays += other.primary_rays;
+        self.closest_hit_rays += other.closest_hit_rays;
+        self.shadow_rays += other.shadow_rays;
+        self.intersection_tests += other.intersection_tests;
+    }
+}
+
+thread_local! {
+    /// Counts of the current thread, which are cheap to increment from the hot paths of the renderer
+    static THREAD_STATS: Cell<RayStats> = const { Cell::new(RayStats::ZERO) };
+}
+
+fn count(update: impl FnOnce(&mut RayStats)) {
+    THREAD_STATS.with(|cell| {
+        let mut stats = cell.get();
+        update(&mut stats);
+        cell.set(stats);
+    });
+}
+
+/// Counts a ray spawned by the camera
+pub fn count_primary_ray() {
+    count(|stats| stats.primary_rays += 1);
+}
+
+/// Counts a ray searched for its closest intersection
+pub fn count_closest_hit_ray() {
+    count(|stats| stats.closest_hit_rays += 1);
+}
+
+/// Counts a ray tested for occlusion
+pub fn count_shadow_ray() {
+    count(|stats| stats.shadow_rays += 1);
+}
+
+/// Counts an intersection test of a ray with a single primitive
+pub fn count_intersection_test() {
+    count(|stats| stats.intersection_tests += 1);
+}
+
+/// Returns the counts of the current thread since the last call and resets them
+pub fn take_thread_stats() -> RayStats {
+    THREAD_STATS.with(|cell| cell.replace(RayStats::ZERO))
+}
+
+/// Durations of the stages of a render
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RenderTimings {
+    /// reading and parsing the scene file, `None` for scenes passed in memory
+    pub scene_load: Option<Duration>,
+    /// loading textures and building the acceleration structures, `None` for scenes passed in memory
+    pub precompute: Option<Duration>,
+    /// rendering the image
+    pub render: Duration,
+}
+
+/// Position of a render in its passes over the tiles of all cameras
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RenderProgress {
+    /// number of finished passes
+    pub passes: usize,
+    /// number of tiles of the current pass rendered so far
+    pub tiles_done: usize,
+    /// number of tiles of a pass
+    pub tiles: usize,
+}
+
+impl RenderProgress {
+    /// Returns the finished fraction of the render between `0.0` and `1.0`.
+    /// A progressive render is finished after its target number of passes or its time limit,
+    /// whichever is reached first, otherwise the render is finished after a single pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `progressive` stop conditions of a progressive render
+    /// * `elapsed` time since the render was started
+    pub fn fraction(&self, progressive: Option<&Progressive>, elapsed: Duration) -> f64 {
+        let pass_fraction = self.tiles_done as f64 / self.tiles.max(1) as f64;
+        let fraction = match progressive {
+            None => self.passes as f64 + pass_fraction,
+            Some(progressive) => {
+                let by_passes = progressive.target_spp.map_or(0.0, |target| {
+                    (self.passes as f64 + pass_fraction) / target.max(1) as f64
+                });
+                let by_time = progressive.time_limit.map_or(0.0, |limit| {
+                    elapsed.as_secs_f64() / limit.as_secs_f64().max(f64::EPSILON)
+                });
+                by_passes.max(by_time)
+            }
+        };
+        fraction.clamp(0.0, 1.0)
+    }
+}
+
+/// Returns the estimated remaining time of a render or `None` if no progress was made yet
+///
+/// # Arguments
+///
+/// * `start_fraction` finished fraction when the render was started, which is non-zero for resumed renders
+/// * `fraction` finished fraction now
+/// * `elapsed` time since the render was started
+pub fn estimate_remaining(
+    start_fraction: f64,
+    fraction: f64,
+    elapsed: Duration,
+) -> Option<Duration> {
+    let progress = fraction - start_fraction;
+    if progress <= 0.0 {
+        return None;
+    }
+    Some(elapsed.mul_f64((1.0 - fraction) / progress))
+}
+
+/// Formats a duration as `h:mm:ss`
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Prints the progress of a render to stderr, overwriting the previous report in the same line
+pub struct ProgressReporter {
+    start: Instant,
+    /// finished fraction when the reporter was created
+    start_fraction: f64,
+    last_report: Option<Instant>,
+}
+
+impl ProgressReporter {
+    /// Creates a reporter measuring the elapsed time from now on
+    ///
+    /// # Arguments
+    ///
+    /// * `start_fraction` finished fraction of the render, which is non-zero for resumed renders
+    pub fn new(start_fraction: f64) -> ProgressReporter {
+        ProgressReporter {
+            start: Instant::now(),
+            start_fraction,
+            last_report: None,
+        }
+    }
+
+    /// Reports the current progress unless the last report was printed just before
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` position of the render in its passes
+    /// * `progressive` stop conditions of a progressive render
+    pub fn report(&mut self, progress: RenderProgress, progressive: Option<&Progressive>) {
+        if self
+            .last_report
+            .is_some_and(|last_report| last_report.elapsed() < REPORT_INTERVAL)
+        {
+            return;
+        }
+        let elapsed = self.start.elapsed();
+        let fraction = progress.fraction(progressive, elapsed);
+        let eta = match estimate_remaining(self.start_fraction, fraction, elapsed) {
+            Some(remaining) => format_duration(remaining),
+            None => "-".to_string(),
+        };
+        // right after a pass the tiles of the finished pass are shown instead of the empty next one
+        let (pass, tiles_done) = if progress.tiles_done == 0 && progress.passes > 0 {
+            (progress.passes, progress.tiles)
+        } else {
+            (progress.passes + 1, progress.tiles_done)
+        };
+        let pass = match progressive {
+            Some(_) => format!("pass {}, ", pass),
+            None => String::new(),
+        };
+        eprint!(
+            "\r{}tiles {}/{} ({:5.1}%) | elapsed {} | ETA {}   ",
+            pass,
+            tiles_done,
+            progress.tiles,
+            100.0 * fraction,
+            format_duration(elapsed),
+            eta
+        );
+        let _ = std::io::stderr().flush();
+        self.last_report = Some(Instant::now());
+    }
+
+    /// Reports the final progress and ends the line of the progress reports
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` position of the finished render in its passes
+    /// * `progressive` stop conditions of a progressive render
+    pub fn finish(&mut self, progress: RenderProgress, progressive: Option<&Progressive>) {
+        self.last_report = None;
+        self.report(progress, progressive);
+        eprintln!();
+    }
+}
+
+/// Prints the statistics of a finished render to stderr
+///
+/// # Arguments
+///
+/// * `stats` rays traced by the render
+/// * `timings` durations of the stages of the render
+pub fn print_summary(stats: &RayStats, timings: &RenderTimings) {
+    let rays_per_second = stats.total_rays() as f64 / timings.render.as_secs_f64().max(1e-9);
+    eprintln!("render statistics:");
+    if let Some(scene_load) = timings.scene_load {
+        eprintln!("  scene load          {:>12.3}s", scene_load.as_secs_f64());
+    }
+    if let Some(precompute) = timings.precompute {
+        eprintln!("  precompute          {:>12.3}s", precompute.as_secs_f64());
+    }
+    eprintln!(
+        "  render              {:>12.3}s",
+        timings.render.as_secs_f64()
+    );
+    eprintln!("  primary rays        {:>13}", stats.primary_rays);
+    eprintln!("  secondary rays      {:>13}", stats.secondary_rays());
+    eprintln!("  shadow rays         {:>13}", stats.shadow_rays);
+    eprintln!("  intersection tests  {:>13}", stats.intersection_tests);
+    eprintln!("  rays per second     {:>13.0}", rays_per_second);
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::raytracer::progressive::{Progressive, WriteInterval};
+
+    use super::{
+        count_intersection_test, count_primary_ray, estimate_remaining, format_duration,
+        take_thread_stats, RenderProgress,
+    };
+
+    #[test]
+    fn test_thread_stats() {
+        take_thread_stats();
+        count_primary_ray();
+        count_intersection_test();
+        count_intersection_test();
+        let stats = take_thread_stats();
+        assert_eq!(stats.primary_rays, 1);
+        assert_eq!(stats.intersection_tests, 2);
+        assert_eq!(take_thread_stats().intersection_tests, 0);
+    }
+
+    #[test]
+    fn test_progress_fraction() {
+        let progress = RenderProgress {
+            passes: 1,
+            tiles_done: 2,
+            tiles: 8,
+        };
+        let minute = Duration::from_secs(60);
+        assert_eq!(
+            RenderProgress {
+                passes: 0,
+                ..progress
+            }
+            .fraction(None, minute),
+            0.25
+        );
+        let progressive = Progressive {
+            target_spp: Some(5),
+            time_limit: None,
+            write_interval: WriteInterval::Passes(1),
+        };
+        assert_eq!(progress.fraction(Some(&progressive), minute), 0.25);
+        // the time limit finishes the render first
+        let limited = Progressive {
+            time_limit: Some(2 * minute),
+            ..progressive
+        };
+        assert_eq!(progress.fraction(Some(&limited), minute), 0.5);
+    }
+
+    #[test]
+    fn test_estimate_remaining() {
+        let minute = Duration::from_secs(60);
+        assert_eq!(estimate_remaining(0.0, 0.25, minute), Some(3 * minute));
+        // resumed renders only count the progress made since they started
+        assert_eq!(estimate_remaining(0.5, 0.75, minute), Some(minute));
+        assert_eq!(estimate_remaining(0.5, 0.5, minute), None);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(59)), "0:00:59");
+        assert_eq!(
+            format_duration(Duration::from_secs(3 * 3600 + 61)),
+            "3:01:01"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use raytracer_rust::{
+    render, render_with_camera, Camera, CameraConfig, Color, DielectricsMaterial, EmissiveMaterial,
+    Integrator, IntegratorType, LambertianMaterial, Light, Material, MetalMaterial, Object, Plane,
+    PointLight, Ray, RenderSettings, Sampler, Scene, Sphere, SuperSampling, Vector3,
+};
+
+/// Builds a small scene of spheres on a floor lit by a point light and an emissive sphere
+fn build_scene() -> Scene {
+    let sphere = |center: Vector3, material: Material| {
+        Object::Sphere(Sphere {
+            center,
+            radius: 0.5,
+            material,
+            motion: None,
+        })
+    };
+    let objects = vec![
+        Object::Plane(Plane {
+            center: Vector3::new(0.0, 0.0, 0.0),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+            material: Material::Lambertian(LambertianMaterial::new(Color::new(0.8, 0.8, 0.8))),
+        }),
+        sphere(
+            Vector3::new(-1.0, 0.5, 0.0),
+            Material::Metal(MetalMaterial::new(Color::new(0.9, 0.6, 0.3), 0.1)),
+        ),
+        sphere(
+            Vector3::new(0.0, 0.5, 0.0),
+            Material::Dieletrics(DielectricsMaterial::new(Color::new(1.0, 1.0, 1.0), 1.5)),
+        ),
+        sphere(
+            Vector3::new(1.0, 0.5, 0.0),
+            Material::Emissive(EmissiveMaterial::new(Color::new(4.0, 4.0, 4.0))),
+        ),
+    ];
+    let camera = CameraConfig::new(
+        Vector3::new(0.0, 1.0, 4.0),
+        Vector3::new(0.0, 0.5, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+    );
+    let mut scene = Scene::new(camera, 16, 12, Color::new(0.1, 0.1, 0.2), objects);
+    scene.lights.push(Light::Point(PointLight {
+        position: Vector3::new(0.0, 3.0, 2.0),
+        color: Color::new(1.0, 1.0, 1.0),
+        intensity: 10.0,
+    }));
+    scene.precompute();
+    scene
+}
+
+fn test_settings() -> RenderSettings {
+    RenderSettings {
+        ssaa: SuperSampling::Jitter(1),
+        threads: 2,
+        ..RenderSettings::default()
+    }
+}
+
+fn is_black(color: &Color) -> bool {
+    color.r == 0.0 && color.g == 0.0 && color.b == 0.0
+}
+
+#[test]
+fn test_render_scene_built_in_code() {
+    let scene = build_scene();
+    let integrator = IntegratorType::Path.build(4, 2);
+    let framebuffer = render(&scene, &test_settings(), integrator.as_ref());
+
+    assert_eq!((framebuffer.width, framebuffer.height), (16, 12));
+    assert_eq!(framebuffer.pixel_colors.len(), 16 * 12);
+    assert_eq!(framebuffer.to_rgb8().len(), 3 * 16 * 12);
+    let lit = framebuffer
+        .pixel_colors
+        .iter()
+        .filter(|c| !is_black(c))
+        .count();
+    assert!(lit > 16 * 12 / 2);
+}
+
+#[test]
+fn test_render_with_camera() {
+    let scene = build_scene();
+    let integrator = IntegratorType::Direct.build(2, 1);
+    let settings = test_settings();
+    let framebuffer = render(&scene, &settings, integrator.as_ref());
+
+    let camera = Camera::new(&scene.camera, scene.width, scene.height);
+    let same_view = render_with_camera(&scene, &camera, &settings, integrator.as_ref());
+    assert_eq!(same_view.to_rgb8(), framebuffer.to_rgb8());
+
+    let mut config = CameraConfig::new(
+        Vector3::new(0.0, 6.0, 0.1),
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+    );
+    config.fovy = 30.0;
+    let camera = Camera::new(&config, scene.width, scene.height);
+    let top_view = render_with_camera(&scene, &camera, &settings, integrator.as_ref());
+    assert_eq!((top_view.width, top_view.height), (16, 12));
+    assert_ne!(top_view.to_rgb8(), framebuffer.to_rgb8());
+}
+
+#[test]
+fn test_scene_from_yaml() {
+    let yaml = "
+camera:
+  eye: [0, 1, 4]
+  look_at: [0, 0.5, 0]
+  up: [0, 1, 0]
+width: 8
+height: 6
+background: [0.2, 0.2, 0.2]
+lights:
+  - type: Directional
+    direction: [0, -1, 0]
+    color: [1, 1, 1]
+objects:
+  - type: Sphere
+    center: [0, 0.5, 0]
+    radius: 0.5
+    material:
+      type: Lambertian
+      albedo: [0.8, 0.2, 0.2]
+";
+    let mut scene = Scene::from_yaml(yaml).unwrap();
+    assert_eq!((scene.width, scene.height), (8, 6));
+    assert_eq!(scene.objects.len(), 1);
+    scene.precompute();
+
+    let integrator = IntegratorType::Whitted.build(1, 1);
+    let framebuffer = render(&scene, &test_settings(), integrator.as_ref());
+    assert_eq!(framebuffer.pixel_colors.len(), 8 * 6);
+
+    assert!(Scene::from_yaml("width: 8").is_err());
+}
+
+/// Integrator outside of the crate shading hits by the depth of the first intersection
+#[derive(Debug)]
+struct DepthIntegrator;
+
+impl Integrator for DepthIntegrator {
+    fn radiance(&self, scene: &Scene, ray: Ray, sampler: &mut dyn Sampler) -> Color {
+        // draw a number like the built-in integrators to check that the sampler can be used
+        let _ = sampler.get_1d();
+        match scene.get_closest_interesection(&ray) {
+            Some(info) => {
+                let depth = 1.0 / (1.0 + (info.point - ray.origin).len());
+                Color::new(depth, depth, depth)
+            }
+            None => Color::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+#[test]
+fn test_custom_integrator() {
+    let scene = build_scene();
+    let framebuffer = render(&scene, &test_settings(), &DepthIntegrator);
+
+    // the top row looks past the scene, the bottom row at the floor
+    assert!((0..16).all(|x| is_black(&framebuffer.pixel(x, 0))));
+    assert!((0..16).all(|x| !is_black(&framebuffer.pixel(x, 11))));
+}